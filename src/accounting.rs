@@ -0,0 +1,379 @@
+use crate::database::{node_db_path, open_read_only, schema_version};
+use crate::masq_schema::{format_masq, join_wei, KNOWN_SCHEMA_VERSIONS};
+use crate::models::{AllNodesData, NodeData, PaymentThresholds};
+use anyhow::{anyhow, Result};
+use rusqlite::Connection;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+const WEI_PER_GWEI: i128 = 1_000_000_000;
+
+/// A wei amount. Serialized as a decimal string, since amounts do not fit
+/// JSON numbers, and in MASQ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Wei(pub i128);
+
+impl Serialize for Wei {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut wei = serializer.serialize_struct("Wei", 2)?;
+        wei.serialize_field("wei", &self.0.to_string())?;
+        wei.serialize_field("masq", &format_masq(self.0))?;
+        wei.end()
+    }
+}
+
+/// A row of `payable` or `receivable`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Balance {
+    pub wallet: String,
+    pub wei: i128,
+    /// `last_paid_timestamp` or `last_received_timestamp`, in Unix seconds
+    pub timestamp: i64,
+    /// A payment of the payable is pending
+    pub pending: bool,
+}
+
+/// What a node database says the node owes and is owed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NodeAccounts {
+    pub payable: Vec<Balance>,
+    pub receivable: Vec<Balance>,
+    /// `earning_wallet_address` of the `config` table
+    pub earning_wallet: Option<String>,
+}
+
+/// How a debt between two local nodes compares on both sides.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ReconciliationStatus {
+    Matched,
+    Mismatched,
+    /// The debtor owes the creditor, but the creditor records nothing
+    MissingReceivable,
+    /// The creditor is owed, but the debtor records nothing
+    MissingPayable,
+}
+
+/// Wallets a local node is known by.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeWallets {
+    pub node: String,
+    pub earning: Vec<String>,
+    pub consuming: Vec<String>,
+}
+
+/// What one local node owes another, from both databases.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Debt {
+    pub debtor: String,
+    pub creditor: String,
+    /// Wallets of the creditor the debtor's payables are kept under
+    pub creditor_wallets: Vec<String>,
+    /// Wallets of the debtor the creditor's receivables are kept under
+    pub debtor_wallets: Vec<String>,
+    pub payable: Option<Wei>,
+    pub receivable: Option<Wei>,
+    /// Payable less receivable, if both are recorded
+    pub difference: Option<Wei>,
+    /// Latest `last_paid_timestamp` of the payables
+    pub last_paid: Option<i64>,
+    /// Latest `last_received_timestamp` of the receivables
+    pub last_received: Option<i64>,
+    pub payment_pending: bool,
+    /// Debt the debtor's `payment-thresholds` allow since `last_paid`, once
+    /// the debt has matured
+    pub threshold: Option<Wei>,
+    /// The payable exceeds `threshold` and no payment is pending
+    pub overdue: bool,
+    pub status: ReconciliationStatus,
+}
+
+/// A balance with a wallet that belongs to no local node.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UnmatchedBalance {
+    pub node: String,
+    /// `payable` or `receivable`
+    pub table: &'static str,
+    pub wallet: String,
+    pub balance: Wei,
+}
+
+/// A node whose accounts could not be read.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedNode {
+    pub node: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountingReport {
+    /// Unix seconds debt ages are measured at
+    pub at: i64,
+    pub wallets: Vec<NodeWallets>,
+    pub debts: Vec<Debt>,
+    pub unmatched: Vec<UnmatchedBalance>,
+    pub skipped: Vec<SkippedNode>,
+}
+
+fn balances(conn: &Connection, sql: &str) -> Result<Vec<Balance>> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(Balance {
+                wallet: row.get(0)?,
+                wei: join_wei(row.get(1)?, row.get(2)?),
+                timestamp: row.get(3)?,
+                pending: row.get::<_, Option<i64>>(4)?.is_some(),
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+    Ok(rows)
+}
+
+/// Payables, receivables and earning wallet of a node database. Only the
+/// known MASQ schemas are read.
+pub fn read_accounts(db_path: &Path) -> Result<NodeAccounts> {
+    let conn = open_read_only(db_path)?;
+    match schema_version(&conn) {
+        Some(version) if KNOWN_SCHEMA_VERSIONS.contains(&version) => {}
+        Some(version) => return Err(anyhow!("schema version {} is not known", version)),
+        None => return Err(anyhow!("no schema version in the config table")),
+    }
+    Ok(NodeAccounts {
+        payable: balances(
+            &conn,
+            "SELECT wallet_address, balance_high_b, balance_low_b, last_paid_timestamp, \
+             pending_payable_rowid FROM payable",
+        )?,
+        receivable: balances(
+            &conn,
+            "SELECT wallet_address, balance_high_b, balance_low_b, last_received_timestamp, \
+             NULL FROM receivable",
+        )?,
+        earning_wallet: conn
+            .query_row(
+                "SELECT value FROM config WHERE name = 'earning_wallet_address'",
+                [],
+                |row| row.get(0),
+            )
+            .ok()
+            .flatten(),
+    })
+}
+
+/// The debt MASQ pays once a payable is `age` seconds past its last
+/// payment: `debt_threshold_gwei` when the debt matures, falling linearly to
+/// `permanent_debt_allowed_gwei` over `threshold_interval_sec`. `None` while
+/// the debt is not mature.
+pub fn payment_threshold(thresholds: &PaymentThresholds, age: i64) -> Option<i128> {
+    let past_maturity = i128::from(age) - i128::from(thresholds.maturity_threshold_sec);
+    if past_maturity <= 0 {
+        return None;
+    }
+    let debt = i128::from(thresholds.debt_threshold_gwei) * WEI_PER_GWEI;
+    let permanent = i128::from(thresholds.permanent_debt_allowed_gwei) * WEI_PER_GWEI;
+    let interval = i128::from(thresholds.threshold_interval_sec);
+    Some(if past_maturity >= interval {
+        permanent
+    } else {
+        debt - (debt - permanent) * past_maturity / interval
+    })
+}
+
+fn push_unique(wallets: &mut Vec<String>, wallet: &str) {
+    if !wallets.iter().any(|w| w.eq_ignore_ascii_case(wallet)) {
+        wallets.push(wallet.to_string());
+    }
+}
+
+/// Wallets of a node from its logs, its `earning-wallet` setting and its
+/// database.
+fn node_wallets(node: &NodeData, accounts: Option<&NodeAccounts>) -> NodeWallets {
+    let mut earning = Vec::new();
+    let configured = node
+        .config
+        .as_ref()
+        .and_then(|c| c.entries.get("earning-wallet"));
+    let recorded = accounts.and_then(|a| a.earning_wallet.as_ref());
+    for wallet in node
+        .earning_wallets
+        .iter()
+        .chain(configured)
+        .chain(recorded)
+    {
+        push_unique(&mut earning, wallet);
+    }
+    let mut consuming = Vec::new();
+    for wallet in &node.consuming_wallets {
+        push_unique(&mut consuming, wallet);
+    }
+    NodeWallets {
+        node: node.name.clone(),
+        earning,
+        consuming,
+    }
+}
+
+fn debt_between<'a>(
+    debts: &'a mut BTreeMap<(String, String), Debt>,
+    debtor: &str,
+    creditor: &str,
+) -> &'a mut Debt {
+    debts
+        .entry((debtor.to_string(), creditor.to_string()))
+        .or_insert_with(|| Debt {
+            debtor: debtor.to_string(),
+            creditor: creditor.to_string(),
+            creditor_wallets: Vec::new(),
+            debtor_wallets: Vec::new(),
+            payable: None,
+            receivable: None,
+            difference: None,
+            last_paid: None,
+            last_received: None,
+            payment_pending: false,
+            threshold: None,
+            overdue: false,
+            status: ReconciliationStatus::Matched,
+        })
+}
+
+/// Matches every node's payables to the receivables of the node they are
+/// owed to, through the creditor's earning wallets and the debtor's
+/// consuming wallets. A debt is overdue once the payables to a creditor
+/// together exceed the debtor's payment threshold at the age of the latest
+/// payment, with no payment pending.
+pub fn reconcile(
+    nodes: &AllNodesData,
+    accounts: &BTreeMap<String, NodeAccounts>,
+    at: i64,
+) -> AccountingReport {
+    let mut names: Vec<&String> = nodes.keys().collect();
+    names.sort();
+    let wallets: Vec<NodeWallets> = names
+        .iter()
+        .map(|name| node_wallets(&nodes[*name], accounts.get(*name)))
+        .collect();
+
+    let owners = |wallets_of: fn(&NodeWallets) -> &Vec<String>| {
+        let mut owners: BTreeMap<String, &str> = BTreeMap::new();
+        for node in &wallets {
+            for wallet in wallets_of(node) {
+                owners
+                    .entry(wallet.to_lowercase())
+                    .or_insert(node.node.as_str());
+            }
+        }
+        owners
+    };
+    let earning_owners = owners(|w| &w.earning);
+    let consuming_owners = owners(|w| &w.consuming);
+
+    let mut debts: BTreeMap<(String, String), Debt> = BTreeMap::new();
+    let mut unmatched = Vec::new();
+
+    for (node, node_accounts) in accounts {
+        for payable in &node_accounts.payable {
+            match earning_owners.get(&payable.wallet.to_lowercase()) {
+                Some(&creditor) if creditor != node => {
+                    let debt = debt_between(&mut debts, node, creditor);
+                    push_unique(&mut debt.creditor_wallets, &payable.wallet);
+                    debt.payable = Some(Wei(debt.payable.unwrap_or_default().0 + payable.wei));
+                    debt.last_paid = debt.last_paid.max(Some(payable.timestamp));
+                    debt.payment_pending |= payable.pending;
+                }
+                _ => unmatched.push(UnmatchedBalance {
+                    node: node.clone(),
+                    table: "payable",
+                    wallet: payable.wallet.clone(),
+                    balance: Wei(payable.wei),
+                }),
+            }
+        }
+        for receivable in &node_accounts.receivable {
+            match consuming_owners.get(&receivable.wallet.to_lowercase()) {
+                Some(&debtor) if debtor != node => {
+                    let debt = debt_between(&mut debts, debtor, node);
+                    push_unique(&mut debt.debtor_wallets, &receivable.wallet);
+                    debt.receivable =
+                        Some(Wei(debt.receivable.unwrap_or_default().0 + receivable.wei));
+                    debt.last_received = debt.last_received.max(Some(receivable.timestamp));
+                }
+                _ => unmatched.push(UnmatchedBalance {
+                    node: node.clone(),
+                    table: "receivable",
+                    wallet: receivable.wallet.clone(),
+                    balance: Wei(receivable.wei),
+                }),
+            }
+        }
+    }
+
+    let debts = debts
+        .into_values()
+        .map(|mut debt| {
+            debt.status = match (debt.payable, debt.receivable) {
+                (Some(payable), Some(receivable)) if payable == receivable => {
+                    ReconciliationStatus::Matched
+                }
+                (Some(_), Some(_)) => ReconciliationStatus::Mismatched,
+                (Some(_), None) => ReconciliationStatus::MissingReceivable,
+                (None, _) => ReconciliationStatus::MissingPayable,
+            };
+            if let (Some(payable), Some(receivable)) = (debt.payable, debt.receivable) {
+                debt.difference = Some(Wei(payable.0 - receivable.0));
+            }
+            let thresholds = nodes
+                .get(&debt.debtor)
+                .and_then(|node| node.config.as_ref())
+                .and_then(|config| config.payment_thresholds.as_ref());
+            if let (Some(thresholds), Some(last_paid)) = (thresholds, debt.last_paid) {
+                debt.threshold = payment_threshold(thresholds, at - last_paid).map(Wei);
+            }
+            debt.overdue = !debt.payment_pending
+                && matches!((debt.payable, debt.threshold), (Some(p), Some(t)) if p > t);
+            debt
+        })
+        .collect();
+
+    AccountingReport {
+        at,
+        wallets,
+        debts,
+        unmatched,
+        skipped: Vec::new(),
+    }
+}
+
+/// Reconciles the databases of every node in `input_dir`. Nodes without a
+/// readable database of a known schema are listed as skipped.
+pub fn accounting_report(nodes: &AllNodesData, input_dir: &Path, at: i64) -> AccountingReport {
+    let mut accounts = BTreeMap::new();
+    let mut skipped = Vec::new();
+    let names: BTreeSet<&String> = nodes.keys().collect();
+    for name in names {
+        let read = node_db_path(input_dir, name)
+            .ok_or_else(|| anyhow!("no node-data.db"))
+            .and_then(|path| read_accounts(&path));
+        match read {
+            Ok(node_accounts) => {
+                accounts.insert(name.clone(), node_accounts);
+            }
+            Err(e) => skipped.push(SkippedNode {
+                node: name.clone(),
+                reason: e.to_string(),
+            }),
+        }
+    }
+    AccountingReport {
+        skipped,
+        ..reconcile(nodes, &accounts, at)
+    }
+}
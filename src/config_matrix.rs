@@ -0,0 +1,207 @@
+use crate::models::{AllNodesData, NodeConfig};
+use crate::parser::parse_node_descriptor;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+/// One config key across all nodes.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ConfigRow {
+    pub key: String,
+    /// Value per node, in the order of `ConfigMatrix::nodes`; `None` if unset
+    pub values: Vec<Option<String>>,
+    /// True if not every node has the same value
+    pub differs: bool,
+}
+
+/// A combination of settings that is likely a mistake.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ConfigWarning {
+    pub node: String,
+    pub key: String,
+    pub message: String,
+}
+
+/// Every `config.toml` key as a row and every node as a column.
+#[derive(Debug, Serialize, Clone, PartialEq, Default)]
+pub struct ConfigMatrix {
+    pub nodes: Vec<String>,
+    pub rows: Vec<ConfigRow>,
+    pub warnings: Vec<ConfigWarning>,
+}
+
+/// Modes in which a node routes for others and so must be able to find and
+/// be found by the rest of the network.
+const ROUTING_MODES: [&str; 2] = ["standard", "originate-only"];
+
+pub fn compare_configs(nodes: &AllNodesData) -> ConfigMatrix {
+    let mut names: Vec<&String> = nodes
+        .iter()
+        .filter(|(_, node)| node.config.is_some())
+        .map(|(name, _)| name)
+        .collect();
+    names.sort();
+    let configs: Vec<(&String, &NodeConfig)> = names
+        .iter()
+        .filter_map(|name| Some((*name, nodes[*name].config.as_ref()?)))
+        .collect();
+
+    let keys: BTreeSet<&String> = configs
+        .iter()
+        .flat_map(|(_, config)| config.entries.keys())
+        .collect();
+    let rows = keys
+        .into_iter()
+        .map(|key| {
+            let values: Vec<Option<String>> = configs
+                .iter()
+                .map(|(_, config)| config.entries.get(key).cloned())
+                .collect();
+            let differs = values.iter().any(|v| *v != values[0]);
+            ConfigRow {
+                key: key.clone(),
+                values,
+                differs,
+            }
+        })
+        .collect();
+
+    ConfigMatrix {
+        nodes: names.into_iter().cloned().collect(),
+        rows,
+        warnings: find_warnings(&configs),
+    }
+}
+
+fn find_warnings(configs: &[(&String, &NodeConfig)]) -> Vec<ConfigWarning> {
+    let mut warnings = Vec::new();
+    let mut warn = |node: &str, key: &str, message: String| {
+        warnings.push(ConfigWarning {
+            node: node.to_string(),
+            key: key.to_string(),
+            message,
+        })
+    };
+
+    // The chain most nodes are on; the others cannot talk to them
+    let mut chain_counts: HashMap<&str, usize> = HashMap::new();
+    for (_, config) in configs {
+        if let Some(chain) = &config.chain {
+            *chain_counts.entry(chain).or_default() += 1;
+        }
+    }
+    let majority_chain = chain_counts
+        .iter()
+        .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+        .map(|(chain, _)| *chain);
+
+    // Nodes listening on each ip:port, to spot clashes and self-references
+    let mut listeners: HashMap<(String, u16), Vec<&str>> = HashMap::new();
+    for (name, config) in configs {
+        if let (Some(ip), Some(port)) = (&config.ip, config.clandestine_port) {
+            listeners
+                .entry((ip.clone(), port))
+                .or_default()
+                .push(name.as_str());
+        }
+    }
+
+    for (name, config) in configs {
+        let mode = config.neighborhood_mode.as_deref().unwrap_or("standard");
+
+        if let (Some(chain), Some(majority)) = (&config.chain, majority_chain) {
+            if chain != majority {
+                warn(
+                    name,
+                    "chain",
+                    format!("On chain {} while most nodes are on {}", chain, majority),
+                );
+            }
+        }
+
+        if ROUTING_MODES.contains(&mode) && config.neighbors.is_empty() {
+            warn(
+                name,
+                "neighbors",
+                format!(
+                    "No neighbors in {} mode: the node can only be found if others list it",
+                    mode
+                ),
+            );
+        }
+        if mode == "standard" && config.ip.is_none() {
+            warn(
+                name,
+                "ip",
+                "No ip in standard mode: other nodes cannot reach it".to_string(),
+            );
+        }
+        if mode == "zero-hop" && !config.neighbors.is_empty() {
+            warn(
+                name,
+                "neighbors",
+                "Neighbors are ignored in zero-hop mode".to_string(),
+            );
+        }
+
+        for neighbor in &config.neighbors {
+            let Some(descriptor) = parse_node_descriptor(neighbor) else {
+                warn(
+                    name,
+                    "neighbors",
+                    format!("Unparseable neighbor descriptor {}", neighbor),
+                );
+                continue;
+            };
+            if config
+                .chain
+                .as_ref()
+                .is_some_and(|c| *c != descriptor.chain)
+            {
+                warn(
+                    name,
+                    "neighbors",
+                    format!(
+                        "Neighbor {} is on chain {}, not {}",
+                        descriptor.public_key,
+                        descriptor.chain,
+                        config.chain.as_deref().unwrap_or_default()
+                    ),
+                );
+            }
+            let points_at_self = descriptor.ip.as_ref() == config.ip.as_ref()
+                && config
+                    .clandestine_port
+                    .is_some_and(|port| descriptor.ports.contains(&port));
+            if points_at_self {
+                warn(
+                    name,
+                    "neighbors",
+                    format!("Neighbor {} is the node itself", descriptor.public_key),
+                );
+            }
+        }
+
+        if let (Some(ip), Some(port)) = (&config.ip, config.clandestine_port) {
+            let sharing = &listeners[&(ip.clone(), port)];
+            if sharing.len() > 1 {
+                warn(
+                    name,
+                    "clandestine-port",
+                    format!(
+                        "{}:{} is also used by {}",
+                        ip,
+                        port,
+                        sharing
+                            .iter()
+                            .filter(|other| **other != name.as_str())
+                            .copied()
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                );
+            }
+        }
+    }
+
+    warnings
+}
@@ -0,0 +1,568 @@
+use crate::masq_schema::{table_layout, ColumnKind};
+use crate::models::{DatabaseData, TableData};
+use crate::redact::{Redactor, REDACTED};
+use anyhow::Result;
+use rusqlite::hooks::{AuthAction, AuthContext, Authorization};
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{params_from_iter, Batch, Connection, OpenFlags};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A table name that is not a table of the database.
+#[derive(Debug)]
+pub struct UnknownTable(pub String);
+
+impl fmt::Display for UnknownTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no such table: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownTable {}
+
+/// A table query naming a column the table does not have or one that is
+/// redacted, or an SQL query that is not a single read-only `SELECT`.
+#[derive(Debug)]
+pub struct InvalidQuery(pub String);
+
+impl fmt::Display for InvalidQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for InvalidQuery {}
+
+/// How a `ColumnFilter` compares the column with its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOp {
+    Eq,
+    /// Column greater than or equal to the value
+    Min,
+    /// Column less than or equal to the value
+    Max,
+    /// SQL `LIKE` pattern, `%` and `_` being wildcards
+    Like,
+}
+
+impl FilterOp {
+    fn sql(self) -> &'static str {
+        match self {
+            FilterOp::Eq => "=",
+            FilterOp::Min => ">=",
+            FilterOp::Max => "<=",
+            FilterOp::Like => "LIKE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnFilter {
+    pub column: String,
+    pub op: FilterOp,
+    pub value: String,
+}
+
+impl ColumnFilter {
+    /// The filter of a `<op>.<column>=<value>` query parameter, `op` being
+    /// `eq`, `min`, `max` or `like`. Other parameters are not filters.
+    pub fn from_param(key: &str, value: &str) -> Option<Self> {
+        let (op, column) = key.split_once('.')?;
+        let op = match op {
+            "eq" => FilterOp::Eq,
+            "min" => FilterOp::Min,
+            "max" => FilterOp::Max,
+            "like" => FilterOp::Like,
+            _ => return None,
+        };
+        Some(ColumnFilter {
+            column: column.to_string(),
+            op,
+            value: value.to_string(),
+        })
+    }
+}
+
+/// Rows of an SQL query, cut off at the query's row limit.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryResult {
+    #[serde(flatten)]
+    pub table: TableData,
+    /// The query had more rows than were returned
+    pub truncated: bool,
+}
+
+/// Pragmas that only report on the schema; pragmas that take a value
+/// otherwise change settings.
+const SCHEMA_PRAGMAS: [&str; 6] = [
+    "table_info",
+    "table_xinfo",
+    "index_list",
+    "index_info",
+    "index_xinfo",
+    "foreign_key_list",
+];
+
+/// Which rows of a table to fetch, and in what order.
+#[derive(Debug, Clone, Default)]
+pub struct TableQuery {
+    pub offset: usize,
+    /// All rows from `offset` on if unset
+    pub limit: Option<usize>,
+    pub order_by: Option<String>,
+    pub desc: bool,
+    /// All must match
+    pub filters: Vec<ColumnFilter>,
+    /// Text any column must contain, ignoring ASCII case
+    pub search: Option<String>,
+}
+
+/// One page of a table.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TablePage {
+    #[serde(flatten)]
+    pub table: TableData,
+    /// Rows matching the query's filters, on every page
+    pub total_rows: usize,
+    pub offset: usize,
+    /// `schema_version` of the database, from its `config` table
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
+    /// What each column holds, if the schema is known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kinds: Option<Vec<Option<ColumnKind>>>,
+    /// Rendering of the cells of `rows` that read better typed, if the
+    /// schema is known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display: Option<Vec<Vec<Option<String>>>>,
+}
+
+impl TablePage {
+    /// Masks secrets in the rows, along with their renderings.
+    pub fn redact(&mut self, redactor: &Redactor) {
+        redactor.redact_table(&mut self.table);
+        if let Some(display) = &mut self.display {
+            for (row, display_row) in self.table.rows.iter().zip(display) {
+                for (cell, display_cell) in row.iter().zip(display_row) {
+                    if cell.as_str() == Some(REDACTED) {
+                        *display_cell = None;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Database of a node: `{node}/node-data.db`, or `{node}-node-data.db` for
+/// flat input directories.
+pub fn node_db_path(input_dir: &Path, node_name: &str) -> Option<PathBuf> {
+    let nested = input_dir.join(node_name).join("node-data.db");
+    let flat = input_dir.join(format!("{}-node-data.db", node_name));
+    [nested, flat].into_iter().find(|path| path.exists())
+}
+
+const READ_ONLY: OpenFlags = OpenFlags::SQLITE_OPEN_READ_ONLY
+    .union(OpenFlags::SQLITE_OPEN_URI)
+    .union(OpenFlags::SQLITE_OPEN_NO_MUTEX);
+
+/// How long to wait for a node to release a lock before reading the
+/// database as immutable.
+const LOCK_WAIT: Duration = Duration::from_millis(250);
+
+/// Opens a node database without ever writing to it. A database a running
+/// node holds locked is opened as immutable instead, which skips locking and
+/// reads what was last committed to the main file.
+pub fn open_read_only(db_path: &Path) -> Result<Connection> {
+    let conn = Connection::open_with_flags(db_path, READ_ONLY)?;
+    conn.busy_timeout(LOCK_WAIT)?;
+    if check_readable(&conn).is_ok() {
+        return Ok(conn);
+    }
+    let conn = Connection::open_with_flags(immutable_uri(db_path), READ_ONLY)?;
+    check_readable(&conn)?;
+    Ok(conn)
+}
+
+/// Reading the schema fails while another connection holds the database
+/// locked.
+fn check_readable(conn: &Connection) -> rusqlite::Result<()> {
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))
+}
+
+/// `file:` URI of `path` with `immutable=1`, escaping the characters URIs
+/// give a meaning to.
+fn immutable_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file:");
+    // Windows drive letters need a leading slash
+    if path.as_bytes().get(1) == Some(&b':') {
+        uri.push('/');
+    }
+    for c in path.chars() {
+        match c {
+            '%' => uri.push_str("%25"),
+            '?' => uri.push_str("%3f"),
+            '#' => uri.push_str("%23"),
+            c => uri.push(c),
+        }
+    }
+    uri.push_str("?mode=ro&immutable=1");
+    uri
+}
+
+/// Names of the user tables of the database.
+pub fn table_names(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )?;
+    let names = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    Ok(names)
+}
+
+/// `name` as an SQL identifier. Only used for names read from
+/// `sqlite_master`, never for names as requested.
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn columns(conn: &Connection, table_name: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1)")?;
+    let columns = stmt
+        .query_map([table_name], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    Ok(columns)
+}
+
+/// The table of the database called `table_name`, or `UnknownTable`.
+fn existing_table(conn: &Connection, table_name: &str) -> Result<String> {
+    table_names(conn)?
+        .into_iter()
+        .find(|name| name == table_name)
+        .ok_or_else(|| UnknownTable(table_name.to_string()).into())
+}
+
+/// Table names and columns of a database; rows are fetched on demand by
+/// `get_table_data`.
+pub(crate) fn extract_database_structure(db_path: &Path) -> Result<DatabaseData> {
+    let conn = open_read_only(db_path)?;
+    let mut db_data = DatabaseData::default();
+    for table_name in table_names(&conn)? {
+        let columns = columns(&conn, &table_name)?;
+        db_data.tables.insert(
+            table_name,
+            TableData {
+                columns,
+                rows: Vec::new(),
+            },
+        );
+    }
+    Ok(db_data)
+}
+
+pub(crate) fn value_to_json(value: ValueRef) -> serde_json::Value {
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(i) => serde_json::Value::Number(i.into()),
+        ValueRef::Real(f) => serde_json::Number::from_f64(f)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        ValueRef::Text(t) => serde_json::Value::String(String::from_utf8_lossy(t).to_string()),
+        ValueRef::Blob(b) => serde_json::Value::String(format!("<BLOB {} bytes>", b.len())),
+    }
+}
+
+/// Every row of a table. Fails with `UnknownTable` unless `table_name` is
+/// one of the tables listed in `sqlite_master`.
+pub fn get_table_data(db_path: &Path, table_name: &str) -> Result<TableData> {
+    let page = query_table(
+        db_path,
+        table_name,
+        &TableQuery::default(),
+        &Redactor::disabled(),
+    )?;
+    Ok(page.table)
+}
+
+/// A filter value as an SQL parameter. Numbers are bound as numbers so they
+/// compare as such with untyped columns; typed columns convert either way.
+fn parameter(value: &str) -> Value {
+    if let Ok(i) = value.parse::<i64>() {
+        Value::Integer(i)
+    } else if let Ok(f) = value.parse::<f64>() {
+        Value::Real(f)
+    } else {
+        Value::Text(value.to_string())
+    }
+}
+
+/// `text` as a `LIKE` pattern matching only itself, escaped with backslashes.
+fn like_literal(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// The rows of a table selected by `query`, with their total count. Column
+/// names in `query` must be columns of the table, and not ones `redactor`
+/// masks, or the query fails with `InvalidQuery`. Values are only ever
+/// bound as parameters.
+pub fn query_table(
+    db_path: &Path,
+    table_name: &str,
+    query: &TableQuery,
+    redactor: &Redactor,
+) -> Result<TablePage> {
+    let conn = open_read_only(db_path)?;
+    let table_name = existing_table(&conn, table_name)?;
+    let columns = columns(&conn, &table_name)?;
+    let redacted = |column: &str| redactor.is_enabled() && redactor.is_sensitive_name(column);
+    let column = |name: &str| -> Result<String> {
+        if !columns.iter().any(|c| c == name) {
+            return Err(InvalidQuery(format!("no such column: {}", name)).into());
+        }
+        if redacted(name) {
+            return Err(InvalidQuery(format!("column is redacted: {}", name)).into());
+        }
+        Ok(quote_identifier(name))
+    };
+
+    let mut conditions = Vec::new();
+    let mut params = Vec::new();
+    for filter in &query.filters {
+        let column = column(&filter.column)?;
+        params.push(match filter.op {
+            FilterOp::Like => Value::Text(filter.value.clone()),
+            _ => parameter(&filter.value),
+        });
+        conditions.push(format!("{} {} ?{}", column, filter.op.sql(), params.len()));
+    }
+    if let Some(search) = query.search.as_deref().filter(|s| !s.is_empty()) {
+        params.push(Value::Text(format!("%{}%", like_literal(search))));
+        let matches: Vec<String> = columns
+            .iter()
+            .filter(|c| !redacted(c))
+            .map(|c| {
+                format!(
+                    "CAST({} AS TEXT) LIKE ?{} ESCAPE '\\'",
+                    quote_identifier(c),
+                    params.len()
+                )
+            })
+            .collect();
+        if matches.is_empty() {
+            conditions.push("0".to_string());
+        } else {
+            conditions.push(format!("({})", matches.join(" OR ")));
+        }
+    }
+
+    let from = if conditions.is_empty() {
+        quote_identifier(&table_name)
+    } else {
+        format!(
+            "{} WHERE {}",
+            quote_identifier(&table_name),
+            conditions.join(" AND ")
+        )
+    };
+    let total_rows: i64 = conn.query_row(
+        &format!("SELECT count(*) FROM {}", from),
+        params_from_iter(&params),
+        |row| row.get(0),
+    )?;
+
+    let order = match &query.order_by {
+        Some(name) => format!(
+            " ORDER BY {}{}",
+            column(name)?,
+            if query.desc { " DESC" } else { "" }
+        ),
+        None => String::new(),
+    };
+    let limit = query.limit.map_or(-1, |l| l as i64);
+    params.push(Value::Integer(limit));
+    params.push(Value::Integer(query.offset as i64));
+    let mut stmt = conn.prepare(&format!(
+        "SELECT * FROM {}{} LIMIT ?{} OFFSET ?{}",
+        from,
+        order,
+        params.len() - 1,
+        params.len()
+    ))?;
+    let schema_version = schema_version(&conn);
+    let layout = table_layout(schema_version, &table_name, &columns);
+    let column_count = stmt.column_count();
+    let (rows, display): (Vec<_>, Vec<_>) = stmt
+        .query_map(params_from_iter(&params), |row| {
+            let values = (0..column_count)
+                .map(|i| row.get_ref(i))
+                .collect::<Result<Vec<_>, _>>()?;
+            let display = layout.as_ref().map(|layout| {
+                let mut display = layout.render_row(&values);
+                for (cell, column) in display.iter_mut().zip(&columns) {
+                    if redacted(column) {
+                        *cell = None;
+                    }
+                }
+                display
+            });
+            Ok((values.into_iter().map(value_to_json).collect(), display))
+        })?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .unzip();
+
+    Ok(TablePage {
+        table: TableData { columns, rows },
+        total_rows: total_rows as usize,
+        offset: query.offset,
+        schema_version,
+        display: layout
+            .is_some()
+            .then(|| display.into_iter().flatten().collect()),
+        kinds: layout.map(|layout| layout.kinds),
+    })
+}
+
+/// The `schema_version` a MASQ node recorded in its `config` table, if
+/// this looks like a MASQ database at all.
+pub fn schema_version(conn: &Connection) -> Option<u32> {
+    let version: String = conn
+        .query_row(
+            "SELECT CAST(value AS TEXT) FROM config WHERE name = 'schema_version'",
+            [],
+            |row| row.get(0),
+        )
+        .ok()?;
+    version.trim().parse().ok()
+}
+
+/// Whether the console may perform `action`, or why not. Columns
+/// `redact_table` would mask, and every `value` of a name/value table such as
+/// `config`, read as NULL so that neither aliases nor `WHERE` clauses reveal
+/// them.
+fn authorize(
+    action: AuthAction<'_>,
+    redactor: &Redactor,
+    name_value_tables: &HashSet<String>,
+) -> Result<Authorization, String> {
+    match action {
+        AuthAction::Select | AuthAction::Recursive | AuthAction::Function { .. } => {
+            Ok(Authorization::Allow)
+        }
+        AuthAction::Pragma {
+            pragma_name,
+            pragma_value,
+        } => {
+            if pragma_value.is_some() && !SCHEMA_PRAGMAS.contains(&pragma_name) {
+                Err(format!("changing pragma {} is not allowed", pragma_name))
+            } else {
+                Ok(Authorization::Allow)
+            }
+        }
+        AuthAction::Read {
+            table_name,
+            column_name,
+        } => {
+            let redacted = redactor.is_enabled()
+                && (redactor.is_sensitive_name(column_name)
+                    || (column_name == "value" && name_value_tables.contains(table_name)));
+            Ok(if redacted {
+                Authorization::Ignore
+            } else {
+                Authorization::Allow
+            })
+        }
+        action => Err(format!(
+            "only SELECT statements are allowed, not {:?}",
+            action
+        )),
+    }
+}
+
+/// Runs a single `SELECT` against a node database and returns up to `limit`
+/// rows. Besides the connection being read-only, an authorizer refuses
+/// anything but reads, so `ATTACH` and pragma changes fail as well, and the
+/// query is interrupted once it has run for `timeout`. Refused, invalid and
+/// interrupted queries fail with `InvalidQuery`. Redacted columns read as
+/// NULL.
+pub fn run_query(
+    db_path: &Path,
+    sql: &str,
+    limit: usize,
+    timeout: Duration,
+    redactor: &Redactor,
+) -> Result<QueryResult> {
+    let conn = open_read_only(db_path)?;
+    let mut name_value_tables = HashSet::new();
+    for table_name in table_names(&conn)? {
+        let columns = columns(&conn, &table_name)?;
+        if columns.iter().any(|c| c == "name") && columns.iter().any(|c| c == "value") {
+            name_value_tables.insert(table_name);
+        }
+    }
+
+    let refused = Arc::new(Mutex::new(None));
+    let reason = Arc::clone(&refused);
+    let redactor = redactor.clone();
+    conn.authorizer(Some(move |context: AuthContext<'_>| {
+        authorize(context.action, &redactor, &name_value_tables).unwrap_or_else(|refusal| {
+            reason.lock().unwrap().get_or_insert(refusal);
+            Authorization::Deny
+        })
+    }));
+    let deadline = Instant::now() + timeout;
+    conn.progress_handler(1000, Some(move || Instant::now() >= deadline));
+
+    let invalid = |e: rusqlite::Error| -> anyhow::Error {
+        let message = if Instant::now() >= deadline {
+            format!("query ran longer than {} ms", timeout.as_millis())
+        } else {
+            refused
+                .lock()
+                .unwrap()
+                .take()
+                .unwrap_or_else(|| e.to_string())
+        };
+        InvalidQuery(message).into()
+    };
+
+    let only_select = || InvalidQuery("only SELECT statements are allowed".to_string());
+    let mut batch = Batch::new(&conn, sql);
+    let mut stmt = batch.next().map_err(invalid)?.ok_or_else(only_select)?;
+    if !matches!(batch.next(), Ok(None)) {
+        return Err(InvalidQuery("only a single statement is allowed".to_string()).into());
+    }
+    if stmt.column_count() == 0 || !stmt.readonly() {
+        return Err(only_select().into());
+    }
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+
+    let mut rows = Vec::new();
+    let mut truncated = false;
+    let mut result = stmt.query([]).map_err(invalid)?;
+    while let Some(row) = result.next().map_err(invalid)? {
+        if rows.len() == limit {
+            truncated = true;
+            break;
+        }
+        rows.push(
+            (0..columns.len())
+                .map(|i| row.get_ref(i).map(value_to_json))
+                .collect::<Result<_, _>>()?,
+        );
+    }
+
+    Ok(QueryResult {
+        table: TableData { columns, rows },
+        truncated,
+    })
+}
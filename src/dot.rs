@@ -0,0 +1,365 @@
+use crate::models::{GossipEdge, GossipGraph, GossipNode};
+use crate::parser::parse_rate_pack;
+use anyhow::{bail, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// An identifier, number or quoted string; quoted ones are never keywords
+    Id {
+        text: String,
+        quoted: bool,
+    },
+    Arrow,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Equals,
+    Separator,
+}
+
+/// Splits DOT source into tokens, skipping comments.
+fn tokenize(dot: &str) -> Result<Vec<(usize, Token)>> {
+    let mut tokens = Vec::new();
+    let mut chars = dot.char_indices().peekable();
+
+    while let Some((pos, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '{' => Token::LBrace,
+            '}' => Token::RBrace,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '=' => Token::Equals,
+            ';' | ',' => Token::Separator,
+            '-' if matches!(chars.peek(), Some((_, '>' | '-'))) => {
+                chars.next();
+                Token::Arrow
+            }
+            '/' if matches!(chars.peek(), Some((_, '/'))) => {
+                chars.find(|(_, c)| *c == '\n');
+                continue;
+            }
+            '/' if matches!(chars.peek(), Some((_, '*'))) => {
+                chars.next();
+                let mut previous = ' ';
+                for (_, c) in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                continue;
+            }
+            '#' => {
+                chars.find(|(_, c)| *c == '\n');
+                continue;
+            }
+            '"' => {
+                let mut text = String::new();
+                let mut closed = false;
+                while let Some((_, c)) = chars.next() {
+                    match c {
+                        '"' => {
+                            closed = true;
+                            break;
+                        }
+                        // Only escaped quotes are unescaped; `\n` and the like
+                        // are label formatting and kept for the caller
+                        '\\' if matches!(chars.peek(), Some((_, '"'))) => {
+                            text.push('"');
+                            chars.next();
+                        }
+                        c => text.push(c),
+                    }
+                }
+                if !closed {
+                    bail!("Unterminated string starting at byte {}", pos);
+                }
+                Token::Id { text, quoted: true }
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' => {
+                let mut text = c.to_string();
+                while let Some((_, c)) = chars.peek() {
+                    if c.is_alphanumeric() || *c == '_' || *c == '.' {
+                        text.push(*c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                Token::Id {
+                    text,
+                    quoted: false,
+                }
+            }
+            c => bail!("Unexpected character {:?} at byte {}", c, pos),
+        };
+        tokens.push((pos, token));
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, t)| t)
+    }
+
+    fn unexpected<T>(&self) -> Result<T> {
+        match self.tokens.get(self.position) {
+            Some((pos, token)) => bail!("Unexpected {:?} at byte {}", token, pos),
+            None => bail!("Unexpected end of graph"),
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        if self.peek() == Some(&expected) {
+            self.position += 1;
+            Ok(())
+        } else {
+            self.unexpected()
+        }
+    }
+
+    fn id(&mut self) -> Result<String> {
+        match self.peek() {
+            Some(Token::Id { text, .. }) => {
+                let text = text.clone();
+                self.position += 1;
+                Ok(text)
+            }
+            _ => self.unexpected(),
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Id { text, quoted: false }) if text.eq_ignore_ascii_case(keyword))
+    }
+
+    /// Zero or more `[a=b, c=d]` lists, merged.
+    fn attributes(&mut self) -> Result<BTreeMap<String, String>> {
+        let mut attributes = BTreeMap::new();
+        while self.peek() == Some(&Token::LBracket) {
+            self.position += 1;
+            loop {
+                match self.peek() {
+                    Some(Token::RBracket) => {
+                        self.position += 1;
+                        break;
+                    }
+                    Some(Token::Separator) => self.position += 1,
+                    _ => {
+                        let name = self.id()?;
+                        let value = if self.peek() == Some(&Token::Equals) {
+                            self.position += 1;
+                            self.id()?
+                        } else {
+                            "true".to_string()
+                        };
+                        attributes.insert(name, value);
+                    }
+                }
+            }
+        }
+        Ok(attributes)
+    }
+}
+
+/// Collects nodes in order of first appearance.
+#[derive(Default)]
+struct GraphBuilder {
+    nodes: Vec<GossipNode>,
+    index: HashMap<String, usize>,
+    edges: Vec<GossipEdge>,
+}
+
+impl GraphBuilder {
+    fn node(&mut self, public_key: &str) -> &mut GossipNode {
+        let index = match self.index.get(public_key) {
+            Some(index) => *index,
+            None => {
+                self.nodes.push(GossipNode {
+                    public_key: public_key.to_string(),
+                    ..Default::default()
+                });
+                self.index
+                    .insert(public_key.to_string(), self.nodes.len() - 1);
+                self.nodes.len() - 1
+            }
+        };
+        &mut self.nodes[index]
+    }
+}
+
+/// Parses a MASQ gossip dump (`digraph db { ... }`) into a typed graph.
+///
+/// Plain DOT is accepted: attribute statements, graph attributes and
+/// subgraph braces are skipped, and edge chains `a -> b -> c` become one edge
+/// per hop. Node labels are interpreted as MASQ writes them (see
+/// `GossipNode`).
+pub fn parse_gossip_graph(dot: &str) -> Result<GossipGraph> {
+    let mut parser = Parser {
+        tokens: tokenize(dot)?,
+        position: 0,
+    };
+
+    if parser.is_keyword("strict") {
+        parser.position += 1;
+    }
+    if !parser.is_keyword("digraph") && !parser.is_keyword("graph") {
+        return parser.unexpected();
+    }
+    parser.position += 1;
+    if matches!(parser.peek(), Some(Token::Id { .. })) {
+        parser.position += 1;
+    }
+    parser.expect(Token::LBrace)?;
+
+    let mut graph = GraphBuilder::default();
+    let mut depth = 0;
+    loop {
+        match parser.peek() {
+            None => return parser.unexpected(),
+            Some(Token::RBrace) if depth == 0 => {
+                parser.position += 1;
+                break;
+            }
+            Some(Token::RBrace) => {
+                depth -= 1;
+                parser.position += 1;
+            }
+            Some(Token::LBrace) => {
+                depth += 1;
+                parser.position += 1;
+            }
+            Some(Token::Separator) => parser.position += 1,
+            Some(Token::Id { .. }) if parser.is_keyword("subgraph") => {
+                parser.position += 1;
+                if matches!(parser.peek(), Some(Token::Id { .. })) {
+                    parser.position += 1;
+                }
+            }
+            Some(Token::Id { .. })
+                if ["graph", "node", "edge"]
+                    .iter()
+                    .any(|k| parser.is_keyword(k)) =>
+            {
+                parser.position += 1;
+                parser.attributes()?;
+            }
+            Some(Token::Id { .. }) => {
+                let mut ids = vec![parser.id()?];
+                if parser.peek() == Some(&Token::Equals) {
+                    // Graph attribute such as `rankdir=LR`
+                    parser.position += 1;
+                    parser.id()?;
+                    continue;
+                }
+                while parser.peek() == Some(&Token::Arrow) {
+                    parser.position += 1;
+                    ids.push(parser.id()?);
+                }
+                let attributes = parser.attributes()?;
+
+                if let [id] = &ids[..] {
+                    let node = graph.node(id);
+                    node.declared = true;
+                    node.attributes.extend(attributes);
+                } else {
+                    for pair in ids.windows(2) {
+                        graph.node(&pair[0]);
+                        graph.node(&pair[1]);
+                        graph.edges.push(GossipEdge {
+                            from: pair[0].clone(),
+                            to: pair[1].clone(),
+                            attributes: attributes.clone(),
+                        });
+                    }
+                }
+            }
+            Some(_) => return parser.unexpected(),
+        }
+    }
+    if parser.peek().is_some() {
+        return parser.unexpected();
+    }
+
+    let mut nodes = graph.nodes;
+    nodes.iter_mut().for_each(interpret_attributes);
+    Ok(GossipGraph {
+        nodes,
+        edges: graph.edges,
+    })
+}
+
+/// Fills the typed fields of a node from its label and style.
+fn interpret_attributes(node: &mut GossipNode) {
+    node.known_source = node
+        .attributes
+        .get("style")
+        .is_some_and(|s| s.split(',').any(|s| s.trim() == "filled"));
+    node.known_target = node.attributes.get("shape").is_some_and(|s| s == "box");
+
+    let Some(label) = node.attributes.get("label") else {
+        return;
+    };
+    node.label = label
+        .split("\\n")
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    for line in &node.label {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            [flags, version, ref rest @ ..]
+                if flags.len() == 2
+                    && version
+                        .strip_prefix('v')
+                        .is_some_and(|v| v.parse::<u32>().is_ok()) =>
+            {
+                let mut flags = flags.chars();
+                let (Some(accepts), Some(routes)) = (flags.next(), flags.next()) else {
+                    continue;
+                };
+                if !matches!(accepts, 'A' | 'a') || !matches!(routes, 'R' | 'r') {
+                    continue;
+                }
+                node.accepts_connections = Some(accepts == 'A');
+                node.routes_data = Some(routes == 'R');
+                node.version = version[1..].parse().ok();
+                node.country_code = rest.first().map(|c| c.to_string());
+            }
+            _ => {
+                if let Some(rate_pack) = parse_rate_pack(line) {
+                    node.rate_pack = Some(rate_pack);
+                } else if let Some((ip, ports)) = parse_node_addr(line) {
+                    node.ip = Some(ip);
+                    node.ports = ports;
+                }
+            }
+        }
+    }
+}
+
+/// Parses a MASQ node address `ip:port/port/...`.
+fn parse_node_addr(text: &str) -> Option<(String, Vec<u16>)> {
+    let (ip, ports) = text.rsplit_once(':')?;
+    let ip = ip.trim_start_matches('[').trim_end_matches(']');
+    ip.parse::<IpAddr>().ok()?;
+    let ports = ports
+        .split('/')
+        .filter(|p| !p.is_empty())
+        .map(str::parse)
+        .collect::<Result<Vec<u16>, _>>()
+        .ok()?;
+    Some((ip.to_string(), ports))
+}
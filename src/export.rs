@@ -0,0 +1,348 @@
+use crate::dot::parse_gossip_graph;
+use crate::identity::{IdentityRegistry, KEY_PREFIX_LEN};
+use crate::models::{GossipEntry, GossipGraph, GossipNode, NodeData};
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::f64::consts::PI;
+use std::fmt::Write;
+use std::io::{Cursor, Write as _};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Radius of a node in exported SVGs
+const NODE_RADIUS: f64 = 18.0;
+/// Room around the circle of nodes for their labels
+const SVG_MARGIN: f64 = 120.0;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// The dump as logged
+    Dot,
+    GraphMl,
+    Svg,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Dot => "dot",
+            ExportFormat::GraphMl => "graphml",
+            ExportFormat::Svg => "svg",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Dot => "text/vnd.graphviz; charset=utf-8",
+            ExportFormat::GraphMl => "application/graphml+xml; charset=utf-8",
+            ExportFormat::Svg => "image/svg+xml; charset=utf-8",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "dot" => Ok(ExportFormat::Dot),
+            "graphml" => Ok(ExportFormat::GraphMl),
+            "svg" => Ok(ExportFormat::Svg),
+            _ => Err(format!(
+                "unknown export format `{}` (expected dot, graphml or svg)",
+                s
+            )),
+        }
+    }
+}
+
+/// Indexes of the gossip entries from `from` to `to`, both included;
+/// `from` defaults to the first entry and `to` to the last.
+pub fn entry_range(
+    node: &NodeData,
+    from: Option<usize>,
+    to: Option<usize>,
+) -> Result<RangeInclusive<usize>> {
+    let Some(last) = node.gossip.len().checked_sub(1) else {
+        bail!("{} has no gossip entries", node.name);
+    };
+    let (from, to) = (from.unwrap_or(0), to.unwrap_or(last));
+    if from > to || to > last {
+        bail!(
+            "gossip entries {}..={} out of range, {} has {}",
+            from,
+            to,
+            node.name,
+            node.gossip.len()
+        );
+    }
+    Ok(from..=to)
+}
+
+/// `node_1_gossip_3_current-database.svg` for the fourth entry of `node_1`.
+pub fn export_file_name(
+    node_name: &str,
+    index: usize,
+    entry: &GossipEntry,
+    format: ExportFormat,
+) -> String {
+    let tag: String = entry
+        .tag
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-");
+    format!(
+        "{}_gossip_{}_{}.{}",
+        node_name,
+        index,
+        tag,
+        format.extension()
+    )
+}
+
+/// One gossip entry in `format`. Nodes of GraphML and SVG exports are
+/// labelled with the local node they belong to, where known.
+pub fn export_entry(
+    node_name: &str,
+    index: usize,
+    entry: &GossipEntry,
+    format: ExportFormat,
+    registry: &IdentityRegistry,
+) -> Result<String> {
+    if format == ExportFormat::Dot {
+        return Ok(entry.dot.clone());
+    }
+    let graph = parse_gossip_graph(&entry.dot)?;
+    let title = format!(
+        "{} gossip {}: {} at {}",
+        node_name, index, entry.tag, entry.timestamp
+    );
+    Ok(match format {
+        ExportFormat::GraphMl => to_graphml(&graph, &title, registry),
+        _ => to_svg(&graph, &title, registry),
+    })
+}
+
+/// Entries `range` of a node's gossip as a ZIP archive of one file each.
+pub fn export_archive(
+    node: &NodeData,
+    range: RangeInclusive<usize>,
+    format: ExportFormat,
+    registry: &IdentityRegistry,
+) -> Result<Vec<u8>> {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for index in range {
+        let entry = &node.gossip[index];
+        let content = export_entry(&node.name, index, entry, format, registry)?;
+        zip.start_file(
+            export_file_name(&node.name, index, entry, format),
+            zip::write::FileOptions::default(),
+        )?;
+        zip.write_all(content.as_bytes())?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Writes entries `range` of a node's gossip into `dir`, one file each, and
+/// returns the paths written.
+pub fn write_entries(
+    node: &NodeData,
+    range: RangeInclusive<usize>,
+    format: ExportFormat,
+    registry: &IdentityRegistry,
+    dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut written = Vec::new();
+    for index in range {
+        let entry = &node.gossip[index];
+        let path = dir.join(export_file_name(&node.name, index, entry, format));
+        std::fs::write(
+            &path,
+            export_entry(&node.name, index, entry, format, registry)?,
+        )?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+fn label(node: &GossipNode, registry: &IdentityRegistry) -> String {
+    let key: String = node.public_key.chars().take(KEY_PREFIX_LEN).collect();
+    match registry.node_for(&node.public_key) {
+        Some(name) => format!("{} ({})", name, key),
+        None => key,
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn to_graphml(graph: &GossipGraph, title: &str, registry: &IdentityRegistry) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    for (id, domain, kind) in [
+        ("title", "graph", "string"),
+        ("label", "node", "string"),
+        ("publicKey", "node", "string"),
+        ("node", "node", "string"),
+        ("version", "node", "int"),
+        ("countryCode", "node", "string"),
+        ("ip", "node", "string"),
+        ("ports", "node", "string"),
+        ("acceptsConnections", "node", "boolean"),
+        ("routesData", "node", "boolean"),
+    ] {
+        let _ = writeln!(
+            out,
+            "  <key id=\"{0}\" for=\"{1}\" attr.name=\"{0}\" attr.type=\"{2}\"/>",
+            id, domain, kind
+        );
+    }
+    out.push_str("  <graph edgedefault=\"directed\">\n");
+    let _ = writeln!(out, "    <data key=\"title\">{}</data>", escape_xml(title));
+    for node in &graph.nodes {
+        let ports: Vec<String> = node.ports.iter().map(u16::to_string).collect();
+        let data = [
+            ("label", Some(label(node, registry))),
+            ("publicKey", Some(node.public_key.clone())),
+            (
+                "node",
+                registry.node_for(&node.public_key).map(str::to_string),
+            ),
+            ("version", node.version.map(|v| v.to_string())),
+            ("countryCode", node.country_code.clone()),
+            ("ip", node.ip.clone()),
+            ("ports", (!ports.is_empty()).then(|| ports.join("/"))),
+            (
+                "acceptsConnections",
+                node.accepts_connections.map(|b| b.to_string()),
+            ),
+            ("routesData", node.routes_data.map(|b| b.to_string())),
+        ];
+        let _ = writeln!(out, "    <node id=\"{}\">", escape_xml(&node.public_key));
+        for (key, value) in data {
+            if let Some(value) = value {
+                let _ = writeln!(
+                    out,
+                    "      <data key=\"{}\">{}</data>",
+                    key,
+                    escape_xml(&value)
+                );
+            }
+        }
+        out.push_str("    </node>\n");
+    }
+    for edge in &graph.edges {
+        let _ = writeln!(
+            out,
+            "    <edge source=\"{}\" target=\"{}\"/>",
+            escape_xml(&edge.from),
+            escape_xml(&edge.to)
+        );
+    }
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+/// Draws the nodes on a circle, local nodes first by name and then the
+/// others by key, so the same snapshot always renders the same way.
+fn to_svg(graph: &GossipGraph, title: &str, registry: &IdentityRegistry) -> String {
+    let mut nodes: Vec<(&GossipNode, Option<&str>)> = graph
+        .nodes
+        .iter()
+        .map(|n| (n, registry.node_for(&n.public_key)))
+        .collect();
+    nodes.sort_by(|(a, a_name), (b, b_name)| {
+        (a_name.is_none(), a_name, &a.public_key).cmp(&(b_name.is_none(), b_name, &b.public_key))
+    });
+
+    let radius = (nodes.len() as f64 * NODE_RADIUS * 3.0 / (2.0 * PI)).max(120.0);
+    let size = 2.0 * (radius + SVG_MARGIN);
+    let center = size / 2.0;
+    let position = |i: usize| -> (f64, f64) {
+        if nodes.len() == 1 {
+            return (center, center);
+        }
+        let angle = 2.0 * PI * i as f64 / nodes.len() as f64 - PI / 2.0;
+        (center + radius * angle.cos(), center + radius * angle.sin())
+    };
+    let positions: std::collections::HashMap<&str, (f64, f64)> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, (node, _))| (node.public_key.as_str(), position(i)))
+        .collect();
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0:.0}\" height=\"{1:.0}\" viewBox=\"0 0 {0:.0} {1:.0}\" font-family=\"sans-serif\" font-size=\"11\">",
+        size,
+        size + 20.0
+    );
+    let _ = writeln!(out, "  <title>{}</title>", escape_xml(title));
+    out.push_str("  <defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"8\" markerHeight=\"8\" orient=\"auto\"><path d=\"M 0 0 L 10 5 L 0 10 z\" fill=\"#666\"/></marker></defs>\n");
+    out.push_str("  <rect width=\"100%\" height=\"100%\" fill=\"#fff\"/>\n");
+    let _ = writeln!(
+        out,
+        "  <text x=\"10\" y=\"{:.0}\" fill=\"#666\">{}</text>",
+        size + 10.0,
+        escape_xml(title)
+    );
+
+    for edge in &graph.edges {
+        let (Some(&(x1, y1)), Some(&(x2, y2))) = (
+            positions.get(edge.from.as_str()),
+            positions.get(edge.to.as_str()),
+        ) else {
+            continue;
+        };
+        let length = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+        if length <= 2.0 * NODE_RADIUS {
+            continue;
+        }
+        // Stop at the edge of the circles so the arrow stays visible
+        let (dx, dy) = ((x2 - x1) / length, (y2 - y1) / length);
+        let _ = writeln!(
+            out,
+            "  <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#666\" marker-end=\"url(#arrow)\"/>",
+            x1 + dx * NODE_RADIUS,
+            y1 + dy * NODE_RADIUS,
+            x2 - dx * NODE_RADIUS,
+            y2 - dy * NODE_RADIUS
+        );
+    }
+
+    for (node, name) in &nodes {
+        let (x, y) = positions[node.public_key.as_str()];
+        let (fill, dashes) = match name {
+            Some(_) => ("#97c2fc", ""),
+            None => ("#ffffff", " stroke-dasharray=\"4 3\""),
+        };
+        let stroke_width = if node.known_source { 3 } else { 1 };
+        let _ = writeln!(out, "  <g>");
+        let _ = writeln!(out, "    <title>{}</title>", escape_xml(&node.public_key));
+        let _ = writeln!(
+            out,
+            "    <circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{}\" fill=\"{}\" stroke=\"#333\" stroke-width=\"{}\"{}/>",
+            x, y, NODE_RADIUS, fill, stroke_width, dashes
+        );
+        let _ = writeln!(
+            out,
+            "    <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+            x,
+            y + NODE_RADIUS + 14.0,
+            escape_xml(&label(node, registry))
+        );
+        let _ = writeln!(out, "  </g>");
+    }
+    out.push_str("</svg>\n");
+    out
+}
@@ -0,0 +1,541 @@
+use crate::dot::parse_gossip_graph;
+use crate::identity::IdentityRegistry;
+use crate::log_index::FnvHasher;
+use crate::logs::timestamp_millis;
+use crate::models::{AllNodesData, GossipGraph};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+/// How much earlier than it was sent a gossip may be logged as received,
+/// to allow for clock differences between the machines of a test network.
+const CLOCK_SKEW_MS: i64 = 5_000;
+/// How long after it was sent a gossip may still be matched as received.
+const DELIVERY_WINDOW_MS: i64 = 60_000;
+
+/// One gossip entry of one node, parsed.
+pub struct ParsedGossip {
+    pub node: String,
+    /// Position in the node's `gossip` list
+    pub index: usize,
+    pub tag: String,
+    pub timestamp: String,
+    pub millis: i64,
+    pub graph: GossipGraph,
+}
+
+impl ParsedGossip {
+    /// Public key of the node that sent the gossip (drawn filled).
+    pub fn source_key(&self) -> Option<&str> {
+        self.graph
+            .nodes
+            .iter()
+            .find(|n| n.known_source)
+            .map(|n| n.public_key.as_str())
+    }
+
+    /// Public key of the node the gossip was addressed to (drawn as a box).
+    pub fn target_key(&self) -> Option<&str> {
+        self.graph
+            .nodes
+            .iter()
+            .find(|n| n.known_target)
+            .map(|n| n.public_key.as_str())
+    }
+}
+
+/// Parses the gossip of every node, skipping entries without a readable
+/// timestamp or graph. Entries are in time order per node.
+pub fn parse_all_gossip(nodes: &AllNodesData) -> BTreeMap<String, Vec<ParsedGossip>> {
+    nodes
+        .iter()
+        .map(|(name, node)| {
+            let mut entries: Vec<ParsedGossip> = node
+                .gossip
+                .iter()
+                .enumerate()
+                .filter_map(|(index, entry)| {
+                    Some(ParsedGossip {
+                        node: name.clone(),
+                        index,
+                        tag: entry.tag.clone(),
+                        timestamp: entry.timestamp.clone(),
+                        millis: timestamp_millis(&entry.timestamp)?,
+                        graph: parse_gossip_graph(&entry.dot).ok()?,
+                    })
+                })
+                .collect();
+            entries.sort_by_key(|e| (e.millis, e.index));
+            (name.clone(), entries)
+        })
+        .collect()
+}
+
+/// Public key of each node, from the nodes it marks as itself in its own
+/// gossip: the sender of what it sent and the target of what it received.
+pub fn node_public_keys(gossip: &BTreeMap<String, Vec<ParsedGossip>>) -> BTreeMap<String, String> {
+    let mut keys = BTreeMap::new();
+    for (node, entries) in gossip {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for entry in entries {
+            let own = match entry.tag.as_str() {
+                "Sent Gossip" => entry.source_key(),
+                "Received Gossip" => entry.target_key(),
+                _ => None,
+            };
+            if let Some(key) = own {
+                *counts.entry(key).or_default() += 1;
+            }
+        }
+        let most_common = counts
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(a.0)))
+            .map(|(key, _)| key.to_string());
+        if let Some(key) = most_common {
+            keys.insert(node.clone(), key);
+        }
+    }
+    keys
+}
+
+/// Identifies the content of a gossip regardless of how sender and target
+/// are drawn, so the sent and received dumps of the same gossip match.
+pub fn gossip_fingerprint(graph: &GossipGraph) -> String {
+    let mut nodes: Vec<_> = graph
+        .nodes
+        .iter()
+        .map(|n| {
+            (
+                &n.public_key,
+                n.version,
+                n.accepts_connections,
+                n.routes_data,
+                &n.ip,
+                &n.ports,
+                n.rate_pack.map(|r| {
+                    (
+                        r.routing_byte_rate,
+                        r.routing_service_rate,
+                        r.exit_byte_rate,
+                        r.exit_service_rate,
+                    )
+                }),
+            )
+        })
+        .collect();
+    nodes.sort();
+    let mut edges: Vec<(&String, &String)> = graph.edges.iter().map(|e| (&e.from, &e.to)).collect();
+    edges.sort();
+
+    let mut hasher = FnvHasher::default();
+    nodes.hash(&mut hasher);
+    edges.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// A sent gossip and, if found, the same gossip received by its target.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GossipHop {
+    pub from_node: String,
+    /// The node the gossip was addressed to, if it is one of ours
+    pub to_node: Option<String>,
+    pub target_key: Option<String>,
+    pub sent_index: usize,
+    pub sent_at: String,
+    pub received_index: Option<usize>,
+    pub received_at: Option<String>,
+    /// Negative if the receiver's clock is behind the sender's
+    pub delay_ms: Option<i64>,
+    pub fingerprint: String,
+}
+
+/// When one node first received a node record version.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GossipArrival {
+    pub node: String,
+    pub received_at: String,
+    /// Time since the version was first gossiped
+    pub delay_ms: i64,
+    /// The node it was received from, if it is one of ours
+    pub via: Option<String>,
+}
+
+/// How one version of one node record spread through the network.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GossipPropagation {
+    pub public_key: String,
+    /// The node the record describes, if it is one of ours
+    pub subject_node: Option<String>,
+    pub version: u32,
+    pub origin_node: String,
+    pub origin_at: String,
+    /// Earliest first, one per node that received the version
+    pub arrivals: Vec<GossipArrival>,
+    /// Nodes with a known key that never received the version
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GossipCorrelation {
+    /// Most reliable public key of each local node
+    pub node_keys: BTreeMap<String, String>,
+    pub hops: Vec<GossipHop>,
+    pub propagations: Vec<GossipPropagation>,
+}
+
+/// Pairs every `Sent Gossip` with the matching `Received Gossip` on the
+/// target node (same content, received within `DELIVERY_WINDOW_MS`) and
+/// traces how each node record version spread from its first sender. Keys
+/// are tied to nodes through `registry`.
+pub fn correlate_gossip(nodes: &AllNodesData, registry: &IdentityRegistry) -> GossipCorrelation {
+    let gossip = parse_all_gossip(nodes);
+    let node_keys = registry.node_keys();
+
+    // Received gossip by node and fingerprint, in time order
+    let mut received: HashMap<(&str, String), Vec<&ParsedGossip>> = HashMap::new();
+    for entry in gossip.values().flatten() {
+        if entry.tag == "Received Gossip" {
+            received
+                .entry((entry.node.as_str(), gossip_fingerprint(&entry.graph)))
+                .or_default()
+                .push(entry);
+        }
+    }
+
+    let mut sent: Vec<&ParsedGossip> = gossip
+        .values()
+        .flatten()
+        .filter(|e| e.tag == "Sent Gossip")
+        .collect();
+    sent.sort_by_key(|e| e.millis);
+
+    let mut matched: HashSet<(&str, usize)> = HashSet::new();
+    let mut hops = Vec::new();
+    for entry in sent {
+        let fingerprint = gossip_fingerprint(&entry.graph);
+        let target_key = entry.target_key();
+        let to_node = target_key.and_then(|key| registry.node_for(key));
+
+        let receipt = to_node
+            .and_then(|node| received.get(&(node, fingerprint.clone())))
+            .and_then(|candidates| {
+                candidates
+                    .iter()
+                    .filter(|r| !matched.contains(&(r.node.as_str(), r.index)))
+                    .filter(|r| {
+                        let delay = r.millis - entry.millis;
+                        (-CLOCK_SKEW_MS..=DELIVERY_WINDOW_MS).contains(&delay)
+                    })
+                    .min_by_key(|r| (r.millis - entry.millis).abs())
+                    .copied()
+            });
+        if let Some(receipt) = receipt {
+            matched.insert((receipt.node.as_str(), receipt.index));
+        }
+
+        hops.push(GossipHop {
+            from_node: entry.node.clone(),
+            to_node: to_node.map(str::to_string),
+            target_key: target_key.map(str::to_string),
+            sent_index: entry.index,
+            sent_at: entry.timestamp.clone(),
+            received_index: receipt.map(|r| r.index),
+            received_at: receipt.map(|r| r.timestamp.clone()),
+            delay_ms: receipt.map(|r| r.millis - entry.millis),
+            fingerprint,
+        });
+    }
+
+    GossipCorrelation {
+        propagations: trace_propagations(&gossip, &node_keys, registry),
+        node_keys,
+        hops,
+    }
+}
+
+fn trace_propagations(
+    gossip: &BTreeMap<String, Vec<ParsedGossip>>,
+    node_keys: &BTreeMap<String, String>,
+    registry: &IdentityRegistry,
+) -> Vec<GossipPropagation> {
+    // First sender of each record version, and first receipt per node
+    let mut origins: HashMap<(&str, u32), &ParsedGossip> = HashMap::new();
+    let mut arrivals: HashMap<(&str, u32), BTreeMap<&str, &ParsedGossip>> = HashMap::new();
+    for entry in gossip.values().flatten() {
+        for node in &entry.graph.nodes {
+            let Some(version) = node.version else {
+                continue;
+            };
+            let state = (node.public_key.as_str(), version);
+            match entry.tag.as_str() {
+                "Sent Gossip" => {
+                    let origin = origins.entry(state).or_insert(entry);
+                    if entry.millis < origin.millis {
+                        *origin = entry;
+                    }
+                }
+                "Received Gossip" => {
+                    let first = arrivals
+                        .entry(state)
+                        .or_default()
+                        .entry(entry.node.as_str())
+                        .or_insert(entry);
+                    if entry.millis < first.millis {
+                        *first = entry;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut propagations: Vec<GossipPropagation> = origins
+        .into_iter()
+        .map(|((public_key, version), origin)| {
+            let subject_node = registry.node_for(public_key);
+            let mut received: Vec<GossipArrival> = arrivals
+                .get(&(public_key, version))
+                .into_iter()
+                .flatten()
+                .filter(|(node, _)| **node != origin.node && Some(**node) != subject_node)
+                .map(|(node, entry)| GossipArrival {
+                    node: node.to_string(),
+                    received_at: entry.timestamp.clone(),
+                    delay_ms: entry.millis - origin.millis,
+                    via: entry
+                        .source_key()
+                        .and_then(|key| registry.node_for(key))
+                        .map(str::to_string),
+                })
+                .collect();
+            received.sort_by_key(|a| a.delay_ms);
+
+            let missing = node_keys
+                .keys()
+                .filter(|node| {
+                    **node != origin.node
+                        && Some(node.as_str()) != subject_node
+                        && !received.iter().any(|a| a.node == **node)
+                })
+                .cloned()
+                .collect();
+
+            GossipPropagation {
+                public_key: public_key.to_string(),
+                subject_node: subject_node.map(str::to_string),
+                version,
+                origin_node: origin.node.clone(),
+                origin_at: origin.timestamp.clone(),
+                arrivals: received,
+                missing,
+            }
+        })
+        .collect();
+    propagations.sort_by(|a, b| {
+        a.origin_at
+            .cmp(&b.origin_at)
+            .then_with(|| a.public_key.cmp(&b.public_key))
+            .then(a.version.cmp(&b.version))
+    });
+    propagations
+}
+
+/// Identifies the topology of a gossip graph (which nodes are known and how
+/// they are linked), ignoring node attributes.
+pub fn topology_fingerprint(graph: &GossipGraph) -> String {
+    let mut keys: Vec<&String> = graph.nodes.iter().map(|n| &n.public_key).collect();
+    keys.sort();
+    let mut edges: Vec<(&String, &String)> = graph.edges.iter().map(|e| (&e.from, &e.to)).collect();
+    edges.sort();
+
+    let mut hasher = FnvHasher::default();
+    keys.hash(&mut hasher);
+    edges.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Agreement between the nodes' views right after one of them changed.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConvergenceSnapshot {
+    pub timestamp: String,
+    /// The node whose `Current database` changed
+    pub node: String,
+    /// Nodes sharing the same topology, largest group first; nodes that have
+    /// not dumped their database yet are left out
+    pub groups: Vec<Vec<String>>,
+    /// Peers known to some node but missing from each node's view, by node
+    /// name where known, otherwise by public key
+    pub missing: BTreeMap<String, Vec<String>>,
+    /// Every node has dumped its database and all agree
+    pub converged: bool,
+}
+
+/// A node record appearing in the network for the first time.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeJoin {
+    pub public_key: String,
+    pub node: Option<String>,
+    pub joined_at: String,
+    pub first_seen_by: String,
+    /// When all nodes first agreed on a topology including the record
+    pub converged_at: Option<String>,
+    pub convergence_ms: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GossipConvergence {
+    /// Nodes that dumped their database at least once
+    pub nodes: Vec<String>,
+    pub snapshots: Vec<ConvergenceSnapshot>,
+    pub joins: Vec<NodeJoin>,
+    /// Whether the last views of all nodes agree
+    pub converged: bool,
+    /// Since when the nodes agree, if they do at the end
+    pub converged_since: Option<String>,
+}
+
+/// Replays the `Current database` dumps of all nodes in time order and
+/// records after each change of a node's view which nodes agree on the
+/// topology, and how long the network took to agree after each node joined.
+/// Keys are named after their nodes through `registry`.
+pub fn analyze_convergence(nodes: &AllNodesData, registry: &IdentityRegistry) -> GossipConvergence {
+    let gossip = parse_all_gossip(nodes);
+    let peer_name = |key: &str| registry.node_for(key).unwrap_or(key).to_string();
+
+    let mut dumps: Vec<&ParsedGossip> = gossip
+        .values()
+        .flatten()
+        .filter(|e| e.tag == "Current database")
+        .collect();
+    dumps.sort_by_key(|e| e.millis);
+    let observers: Vec<String> = dumps
+        .iter()
+        .map(|e| e.node.clone())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    // Latest view of each node: topology fingerprint and known keys
+    let mut views: BTreeMap<&str, (String, HashSet<&str>)> = BTreeMap::new();
+    let mut snapshots: Vec<ConvergenceSnapshot> = Vec::new();
+    let mut snapshot_millis: Vec<i64> = Vec::new();
+    let mut joins: Vec<(NodeJoin, i64)> = Vec::new();
+    let mut seen: HashSet<&str> = HashSet::new();
+
+    for dump in dumps {
+        let fingerprint = topology_fingerprint(&dump.graph);
+        let keys: HashSet<&str> = dump
+            .graph
+            .nodes
+            .iter()
+            .map(|n| n.public_key.as_str())
+            .collect();
+
+        for node in &dump.graph.nodes {
+            if seen.insert(node.public_key.as_str()) {
+                joins.push((
+                    NodeJoin {
+                        public_key: node.public_key.clone(),
+                        node: registry.node_for(&node.public_key).map(str::to_string),
+                        joined_at: dump.timestamp.clone(),
+                        first_seen_by: dump.node.clone(),
+                        converged_at: None,
+                        convergence_ms: None,
+                    },
+                    dump.millis,
+                ));
+            }
+        }
+
+        if views
+            .get(dump.node.as_str())
+            .is_some_and(|(previous, _)| *previous == fingerprint)
+        {
+            continue;
+        }
+        views.insert(dump.node.as_str(), (fingerprint, keys));
+
+        let mut groups: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for (node, (fingerprint, _)) in &views {
+            groups
+                .entry(fingerprint.as_str())
+                .or_default()
+                .push(node.to_string());
+        }
+        let mut groups: Vec<Vec<String>> = groups.into_values().collect();
+        groups.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+
+        let all_keys: HashSet<&str> = views
+            .values()
+            .flat_map(|(_, keys)| keys.iter().copied())
+            .collect();
+        let missing = views
+            .iter()
+            .filter_map(|(node, (_, keys))| {
+                let mut absent: Vec<String> = all_keys
+                    .iter()
+                    .filter(|key| !keys.contains(*key))
+                    .map(|key| peer_name(key))
+                    .collect();
+                absent.sort();
+                (!absent.is_empty()).then(|| (node.to_string(), absent))
+            })
+            .collect();
+
+        snapshots.push(ConvergenceSnapshot {
+            timestamp: dump.timestamp.clone(),
+            node: dump.node.clone(),
+            converged: groups.len() == 1 && views.len() == observers.len(),
+            groups,
+            missing,
+        });
+        snapshot_millis.push(dump.millis);
+    }
+
+    // A join has converged once all nodes agree and the record is in view
+    for (join, joined_millis) in &mut joins {
+        let converged = snapshots
+            .iter()
+            .zip(&snapshot_millis)
+            .filter(|(_, millis)| **millis >= *joined_millis)
+            .find(|(snapshot, _)| {
+                snapshot.converged
+                    && !snapshot
+                        .missing
+                        .values()
+                        .flatten()
+                        .any(|peer| *peer == peer_name(&join.public_key))
+            });
+        if let Some((snapshot, millis)) = converged {
+            join.converged_at = Some(snapshot.timestamp.clone());
+            join.convergence_ms = Some(millis - *joined_millis);
+        }
+    }
+
+    let converged = snapshots.last().is_some_and(|s| s.converged);
+    let converged_since = converged
+        .then(|| {
+            snapshots
+                .iter()
+                .rev()
+                .take_while(|s| s.converged)
+                .last()
+                .map(|s| s.timestamp.clone())
+        })
+        .flatten();
+
+    GossipConvergence {
+        nodes: observers,
+        snapshots,
+        joins: joins.into_iter().map(|(join, _)| join).collect(),
+        converged,
+        converged_since,
+    }
+}
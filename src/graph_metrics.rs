@@ -0,0 +1,277 @@
+use crate::dot::parse_gossip_graph;
+use crate::identity::IdentityRegistry;
+use crate::models::{GossipGraph, NodeData};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// Relays a node needs between itself and the exit of a route.
+const ROUTE_HOPS: usize = 3;
+
+/// Whether a local node in a snapshot has enough neighbors to route.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalRouteCheck {
+    pub node: String,
+    pub public_key: String,
+    /// One three-hop route the node could build, if any
+    pub route: Option<Vec<String>>,
+}
+
+/// Shape and health of one gossip snapshot.
+#[derive(Debug, Serialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphMetrics {
+    pub node_count: usize,
+    /// Edges as dumped, one per direction
+    pub edge_count: usize,
+    /// Number of nodes with each neighbor count, ignoring edge direction
+    pub degree_distribution: BTreeMap<usize, usize>,
+    /// Connected components, ignoring edge direction, largest first
+    pub components: Vec<Vec<String>>,
+    /// Longest shortest path between two connected nodes, in edges
+    pub diameter: Option<usize>,
+    /// Nodes whose loss would split their component
+    pub articulation_points: Vec<String>,
+    /// Local nodes in the snapshot and whether each can build a route
+    pub local_routes: Vec<LocalRouteCheck>,
+    /// Every local node in the snapshot can build a three-hop route; false
+    /// when no local node is in it
+    pub all_local_routable: bool,
+}
+
+/// Metrics of one gossip entry of a node, for charting.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphMetricsPoint {
+    /// Position in the node's `gossip` list
+    pub index: usize,
+    pub timestamp: String,
+    pub tag: String,
+    pub metrics: GraphMetrics,
+}
+
+/// Neighbors of every node, ignoring edge direction.
+fn undirected(graph: &GossipGraph) -> BTreeMap<&str, BTreeSet<&str>> {
+    let mut adjacency: BTreeMap<&str, BTreeSet<&str>> = graph
+        .nodes
+        .iter()
+        .map(|n| (n.public_key.as_str(), BTreeSet::new()))
+        .collect();
+    for edge in graph.edges.iter().filter(|e| e.from != e.to) {
+        adjacency
+            .entry(&edge.from)
+            .or_default()
+            .insert(edge.to.as_str());
+        adjacency
+            .entry(&edge.to)
+            .or_default()
+            .insert(edge.from.as_str());
+    }
+    adjacency
+}
+
+/// Distance from `start` to every node reachable from it.
+fn distances<'a>(
+    adjacency: &BTreeMap<&'a str, BTreeSet<&'a str>>,
+    start: &'a str,
+) -> BTreeMap<&'a str, usize> {
+    let mut distance = BTreeMap::from([(start, 0)]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        let next = distance[node] + 1;
+        for &neighbor in &adjacency[node] {
+            if !distance.contains_key(neighbor) {
+                distance.insert(neighbor, next);
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    distance
+}
+
+fn connected_components<'a>(adjacency: &BTreeMap<&'a str, BTreeSet<&'a str>>) -> Vec<Vec<&'a str>> {
+    let mut seen: BTreeSet<&str> = BTreeSet::new();
+    let mut components = Vec::new();
+    for &node in adjacency.keys() {
+        if seen.contains(node) {
+            continue;
+        }
+        let component: Vec<&str> = distances(adjacency, node).into_keys().collect();
+        seen.extend(&component);
+        components.push(component);
+    }
+    components
+}
+
+/// Nodes whose loss would split their component, found with Tarjan's
+/// lowpoint depth-first search.
+fn articulation_points<'a>(adjacency: &BTreeMap<&'a str, BTreeSet<&'a str>>) -> BTreeSet<&'a str> {
+    /// Visits `node` and returns the earliest discovery reachable from below
+    /// it through at most one edge that is not on the search tree.
+    fn visit<'a>(
+        node: &'a str,
+        parent: Option<&'a str>,
+        adjacency: &BTreeMap<&'a str, BTreeSet<&'a str>>,
+        discovered: &mut BTreeMap<&'a str, usize>,
+        points: &mut BTreeSet<&'a str>,
+    ) -> usize {
+        let order = discovered.len();
+        discovered.insert(node, order);
+        let mut low = order;
+        let mut children = 0;
+        for &neighbor in &adjacency[node] {
+            if Some(neighbor) == parent {
+                continue;
+            }
+            match discovered.get(neighbor) {
+                Some(&seen) => low = low.min(seen),
+                None => {
+                    children += 1;
+                    let below = visit(neighbor, Some(node), adjacency, discovered, points);
+                    low = low.min(below);
+                    // Nothing under the child reaches above this node
+                    if parent.is_some() && below >= order {
+                        points.insert(node);
+                    }
+                }
+            }
+        }
+        // The root splits the component if the search left it more than once
+        if parent.is_none() && children > 1 {
+            points.insert(node);
+        }
+        low
+    }
+
+    let mut discovered = BTreeMap::new();
+    let mut points = BTreeSet::new();
+    for &node in adjacency.keys() {
+        if !discovered.contains_key(node) {
+            visit(node, None, adjacency, &mut discovered, &mut points);
+        }
+    }
+    points
+}
+
+/// A route of `ROUTE_HOPS` distinct nodes after `origin`, each a full
+/// neighbor (linked in both directions) of the one before. Relays must not
+/// have declared that they refuse to route data.
+fn find_route(graph: &GossipGraph, origin: &str) -> Option<Vec<String>> {
+    let links: BTreeSet<(&str, &str)> = graph
+        .edges
+        .iter()
+        .map(|e| (e.from.as_str(), e.to.as_str()))
+        .collect();
+    let mut full_neighbors: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for &(from, to) in &links {
+        if from != to && links.contains(&(to, from)) {
+            full_neighbors.entry(from).or_default().push(to);
+        }
+    }
+    let relays: BTreeSet<&str> = graph
+        .nodes
+        .iter()
+        .filter(|n| n.routes_data != Some(false))
+        .map(|n| n.public_key.as_str())
+        .collect();
+
+    fn extend<'a>(
+        path: &mut Vec<&'a str>,
+        full_neighbors: &BTreeMap<&'a str, Vec<&'a str>>,
+        relays: &BTreeSet<&str>,
+    ) -> bool {
+        if path.len() == ROUTE_HOPS + 1 {
+            return true;
+        }
+        let last = path[path.len() - 1];
+        // Every hop between the origin and the exit relays for others
+        if path.len() > 1 && !relays.contains(last) {
+            return false;
+        }
+        for &next in full_neighbors.get(last).into_iter().flatten() {
+            if path.contains(&next) {
+                continue;
+            }
+            path.push(next);
+            if extend(path, full_neighbors, relays) {
+                return true;
+            }
+            path.pop();
+        }
+        false
+    }
+
+    let mut path = vec![origin];
+    extend(&mut path, &full_neighbors, &relays)
+        .then(|| path[1..].iter().map(|key| key.to_string()).collect())
+}
+
+/// Counts, connectivity and routability of a gossip snapshot. Local nodes
+/// are recognised through `registry`.
+pub fn graph_metrics(graph: &GossipGraph, registry: &IdentityRegistry) -> GraphMetrics {
+    let adjacency = undirected(graph);
+
+    let mut degree_distribution = BTreeMap::new();
+    for neighbors in adjacency.values() {
+        *degree_distribution.entry(neighbors.len()).or_default() += 1;
+    }
+
+    let mut components = connected_components(&adjacency);
+    components.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+
+    let diameter = adjacency
+        .keys()
+        .filter_map(|node| distances(&adjacency, node).into_values().max())
+        .max();
+
+    let articulation_points = articulation_points(&adjacency)
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+
+    let mut local_routes: Vec<LocalRouteCheck> = graph
+        .nodes
+        .iter()
+        .filter_map(|n| {
+            let node = registry.node_for(&n.public_key)?;
+            Some(LocalRouteCheck {
+                node: node.to_string(),
+                public_key: n.public_key.clone(),
+                route: find_route(graph, &n.public_key),
+            })
+        })
+        .collect();
+    local_routes.sort_by(|a, b| a.node.cmp(&b.node));
+
+    GraphMetrics {
+        node_count: graph.nodes.len(),
+        edge_count: graph.edges.len(),
+        degree_distribution,
+        components: components
+            .into_iter()
+            .map(|c| c.into_iter().map(str::to_string).collect())
+            .collect(),
+        diameter,
+        articulation_points,
+        all_local_routable: !local_routes.is_empty()
+            && local_routes.iter().all(|r| r.route.is_some()),
+        local_routes,
+    }
+}
+
+/// Metrics of every readable gossip entry of a node, in log order.
+pub fn metrics_series(node: &NodeData, registry: &IdentityRegistry) -> Vec<GraphMetricsPoint> {
+    node.gossip
+        .iter()
+        .enumerate()
+        .filter_map(|(index, entry)| {
+            let graph = parse_gossip_graph(&entry.dot).ok()?;
+            Some(GraphMetricsPoint {
+                index,
+                timestamp: entry.timestamp.clone(),
+                tag: entry.tag.clone(),
+                metrics: graph_metrics(&graph, registry),
+            })
+        })
+        .collect()
+}
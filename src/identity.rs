@@ -0,0 +1,206 @@
+use crate::gossip::{node_public_keys, parse_all_gossip};
+use crate::models::AllNodesData;
+use crate::parser::parse_node_descriptor;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Gossip labels abbreviate public keys to their first eight characters.
+/// Shorter prefixes are too ambiguous to resolve to a key.
+pub const KEY_PREFIX_LEN: usize = 8;
+
+/// How a public key was tied to a local node, from most to least reliable.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IdentitySource {
+    /// The `MASQ Node local descriptor` the node logged at startup
+    Descriptor,
+    /// A `neighbors` descriptor of another node pointing at the node's `ip`
+    Neighbor,
+    /// A gossip node advertising the node's `ip`
+    Ip,
+    /// The node marking the key as itself in its own gossip
+    Gossip,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Identity {
+    pub public_key: String,
+    pub node: String,
+    pub source: IdentitySource,
+}
+
+/// A key that appears in gossip but belongs to no local node.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UnknownKey {
+    pub public_key: String,
+    /// Address the key advertised, if any gossip had one
+    pub ip: Option<String>,
+    /// Local nodes whose gossip mentions the key
+    pub seen_by: Vec<String>,
+}
+
+/// Which local node each known public key belongs to.
+#[derive(Debug, Serialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityRegistry {
+    /// Sorted by node, then public key
+    pub identities: Vec<Identity>,
+    pub unknown: Vec<UnknownKey>,
+    /// Normalized key to position in `identities`
+    #[serde(skip)]
+    index: BTreeMap<String, usize>,
+}
+
+/// Descriptors use the URL-safe base64 alphabet and gossip dumps the
+/// standard one, so keys are compared in the standard alphabet without
+/// padding.
+pub fn normalize_key(key: &str) -> String {
+    key.trim_end_matches('=')
+        .chars()
+        .map(|c| match c {
+            '-' => '+',
+            '_' => '/',
+            c => c,
+        })
+        .collect()
+}
+
+impl IdentityRegistry {
+    /// Collects keys from the descriptors nodes logged, the `neighbors` and
+    /// `ip` settings of their configs and their gossip, then lists the
+    /// gossip keys none of them explains.
+    pub fn build(nodes: &AllNodesData) -> Self {
+        let mut names: Vec<&String> = nodes.keys().collect();
+        names.sort();
+
+        // Several keys may claim one node, but one key only one node
+        let mut claims: BTreeMap<String, (IdentitySource, String, String)> = BTreeMap::new();
+        let mut claim = |key: &str, node: &str, source: IdentitySource| {
+            let normalized = normalize_key(key);
+            let better = claims
+                .get(&normalized)
+                .is_none_or(|(existing, _, _)| source < *existing);
+            if better {
+                claims.insert(normalized, (source, node.to_string(), key.to_string()));
+            }
+        };
+
+        let mut node_by_ip: HashMap<&str, &str> = HashMap::new();
+        for name in &names {
+            let node = &nodes[*name];
+            for descriptor in &node.local_descriptors {
+                claim(&descriptor.public_key, name, IdentitySource::Descriptor);
+            }
+            if let Some(ip) = node.config.as_ref().and_then(|c| c.ip.as_deref()) {
+                node_by_ip.entry(ip).or_insert(name);
+            }
+        }
+
+        for name in &names {
+            let neighbors = nodes[*name].config.iter().flat_map(|c| &c.neighbors);
+            for descriptor in neighbors.filter_map(|n| parse_node_descriptor(n)) {
+                if let Some(owner) = descriptor.ip.as_deref().and_then(|ip| node_by_ip.get(ip)) {
+                    claim(&descriptor.public_key, owner, IdentitySource::Neighbor);
+                }
+            }
+        }
+
+        let gossip = parse_all_gossip(nodes);
+        for entry in gossip.values().flatten() {
+            for node in &entry.graph.nodes {
+                if let Some(owner) = node.ip.as_deref().and_then(|ip| node_by_ip.get(ip)) {
+                    claim(&node.public_key, owner, IdentitySource::Ip);
+                }
+            }
+        }
+        for (node, key) in node_public_keys(&gossip) {
+            claim(&key, &node, IdentitySource::Gossip);
+        }
+
+        let mut identities: Vec<Identity> = claims
+            .into_values()
+            .map(|(source, node, public_key)| Identity {
+                public_key,
+                node,
+                source,
+            })
+            .collect();
+        identities.sort_by(|a, b| (&a.node, &a.public_key).cmp(&(&b.node, &b.public_key)));
+        let index = identities
+            .iter()
+            .enumerate()
+            .map(|(i, identity)| (normalize_key(&identity.public_key), i))
+            .collect();
+        let mut registry = IdentityRegistry {
+            identities,
+            unknown: Vec::new(),
+            index,
+        };
+
+        let mut unknown: BTreeMap<&str, (Option<&str>, BTreeSet<&str>)> = BTreeMap::new();
+        for entry in gossip.values().flatten() {
+            let keys = entry
+                .graph
+                .nodes
+                .iter()
+                .map(|n| (n.public_key.as_str(), n.ip.as_deref()));
+            for (key, ip) in keys {
+                if registry.node_for(key).is_none() {
+                    let (known_ip, seen_by) = unknown.entry(key).or_default();
+                    *known_ip = known_ip.or(ip);
+                    seen_by.insert(&entry.node);
+                }
+            }
+        }
+        registry.unknown = unknown
+            .into_iter()
+            .map(|(key, (ip, seen_by))| UnknownKey {
+                public_key: key.to_string(),
+                ip: ip.map(str::to_string),
+                seen_by: seen_by.into_iter().map(str::to_string).collect(),
+            })
+            .collect();
+        registry
+    }
+
+    /// The local node a public key belongs to. Accepts either base64
+    /// alphabet, and the abbreviated keys of gossip labels as long as only
+    /// one known key starts with them.
+    pub fn identity(&self, key: &str) -> Option<&Identity> {
+        let key = normalize_key(key);
+        if let Some(&i) = self.index.get(&key) {
+            return Some(&self.identities[i]);
+        }
+        if key.len() < KEY_PREFIX_LEN {
+            return None;
+        }
+        let mut matches = self
+            .index
+            .range(key.clone()..)
+            .take_while(|(known, _)| known.starts_with(&key));
+        match (matches.next(), matches.next()) {
+            (Some((_, &i)), None) => Some(&self.identities[i]),
+            _ => None,
+        }
+    }
+
+    pub fn node_for(&self, key: &str) -> Option<&str> {
+        self.identity(key).map(|identity| identity.node.as_str())
+    }
+
+    /// The most reliable public key of each local node, in the alphabet of
+    /// the gossip dumps.
+    pub fn node_keys(&self) -> BTreeMap<String, String> {
+        let mut best: BTreeMap<&str, &Identity> = BTreeMap::new();
+        for identity in &self.identities {
+            let known = best.entry(&identity.node).or_insert(identity);
+            if identity.source < known.source {
+                *known = identity;
+            }
+        }
+        best.into_iter()
+            .map(|(node, identity)| (node.to_string(), normalize_key(&identity.public_key)))
+            .collect()
+    }
+}
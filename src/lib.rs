@@ -1,3 +1,19 @@
+pub mod accounting;
+pub mod config_matrix;
+pub mod database;
+pub mod dot;
+pub mod export;
+pub mod gossip;
+pub mod graph_metrics;
+pub mod identity;
+pub mod log_index;
+pub mod logs;
+pub mod masq_schema;
 pub mod models;
 pub mod parser;
+pub mod redact;
 pub mod routes;
+pub mod tail;
+pub mod timeline;
+pub mod topology;
+pub mod watcher;
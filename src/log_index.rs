@@ -0,0 +1,487 @@
+use crate::logs::{decompress_to, detect_encoding, LogEncoding};
+use crate::models::LogRecord;
+use crate::parser::{is_log_head_line, LogRecordReader};
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+const INDEX_MAGIC: &[u8; 8] = b"MLVIDX02";
+
+/// Bytes at each end of the indexed part of a log that are checked before
+/// the index is extended.
+const FINGERPRINT_WINDOW: u64 = 4096;
+
+pub(crate) const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher` it is stable across Rust releases,
+/// so cache file names and fingerprints survive toolchain upgrades.
+pub(crate) fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// `fnv1a` as a `Hasher`, for hashing values that derive `Hash`.
+pub(crate) struct FnvHasher(u64);
+
+impl Default for FnvHasher {
+    fn default() -> Self {
+        FnvHasher(FNV_OFFSET_BASIS)
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0 = fnv1a(self.0, bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Hash of the first and last `FINGERPRINT_WINDOW` bytes of the first `len`
+/// bytes of a file. A log that was replaced rather than appended to differs
+/// in them, if only in its timestamps.
+fn fingerprint(path: &Path, len: u64) -> Result<u64> {
+    let mut file = File::open(path)?;
+    let mut hash = fnv1a(FNV_OFFSET_BASIS, &len.to_le_bytes());
+    let mut bytes = Vec::new();
+    for start in [0, len.saturating_sub(FINGERPRINT_WINDOW)] {
+        bytes.clear();
+        file.seek(SeekFrom::Start(start))?;
+        (&mut file)
+            .take(FINGERPRINT_WINDOW.min(len))
+            .read_to_end(&mut bytes)?;
+        hash = fnv1a(hash, &bytes);
+    }
+    Ok(hash)
+}
+
+/// Cache location used when `--cache-dir` is not given: a folder in the
+/// user's cache directory (`$XDG_CACHE_HOME`, `~/.cache`, ...). Kept out of
+/// the input directory so the visualizer never writes next to a running
+/// node's files, and out of the shared temp directory because decompressed
+/// archives hold the node's secrets in plain text.
+pub fn default_cache_dir() -> PathBuf {
+    match dirs::cache_dir() {
+        Some(dir) => dir.join("masq-log-visualizer"),
+        None => std::env::temp_dir().join(format!("masq-log-visualizer-{}", user_tag())),
+    }
+}
+
+#[cfg(unix)]
+fn user_tag() -> String {
+    // SAFETY: geteuid cannot fail and has no preconditions
+    unsafe { libc::geteuid() }.to_string()
+}
+
+#[cfg(not(unix))]
+fn user_tag() -> String {
+    std::env::var("USERNAME").unwrap_or_default()
+}
+
+/// Creates `dir` readable by its owner only, or checks that an existing one
+/// is a directory of the current user and no one else can read it. Another
+/// user could otherwise read decompressed logs or plant index files.
+fn ensure_private_dir(dir: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)?;
+        let metadata = fs::symlink_metadata(dir)?;
+        if !metadata.is_dir() {
+            bail!("Cache dir {:?} is not a directory", dir);
+        }
+        // SAFETY: geteuid cannot fail and has no preconditions
+        if metadata.uid() != unsafe { libc::geteuid() } {
+            bail!("Cache dir {:?} is owned by another user", dir);
+        }
+        if metadata.mode() & 0o077 != 0 {
+            fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+        }
+    }
+    #[cfg(not(unix))]
+    fs::create_dir_all(dir)?;
+    Ok(())
+}
+
+/// Size and modification time of an indexed file, used for invalidation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SourceStamp {
+    len: u64,
+    mtime_nanos: u64,
+}
+
+impl SourceStamp {
+    fn of(path: &Path) -> Result<Self> {
+        let metadata = fs::metadata(path)?;
+        let mtime_nanos = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Ok(SourceStamp {
+            len: metadata.len(),
+            mtime_nanos,
+        })
+    }
+}
+
+/// Byte offsets of every record in a log, so a page of records can be read
+/// with one seek instead of re-reading the whole file.
+///
+/// Archives cannot be seeked into, so they are decompressed once into the
+/// cache directory and the offsets point into that copy.
+#[derive(Debug, Clone)]
+pub struct LogIndex {
+    stamp: SourceStamp,
+    /// File the offsets point into: the log itself or a decompressed copy
+    text_path: PathBuf,
+    /// True when `text_path` is the log itself, so growth can be indexed incrementally
+    in_place: bool,
+    /// Bytes of `text_path` covered by the index; always ends after a line break
+    indexed_len: u64,
+    /// `fingerprint` of the first `indexed_len` bytes
+    fingerprint: u64,
+    /// Number of complete lines covered by the index
+    line_count: u64,
+    /// Byte offset of the first line of each record
+    offsets: Vec<u64>,
+    /// Zero-based line number of the first line of each record
+    lines: Vec<u64>,
+}
+
+impl LogIndex {
+    fn build(text_path: PathBuf, in_place: bool, stamp: SourceStamp) -> Result<Self> {
+        let mut index = LogIndex {
+            stamp,
+            text_path,
+            in_place,
+            indexed_len: 0,
+            fingerprint: 0,
+            line_count: 0,
+            offsets: Vec::new(),
+            lines: Vec::new(),
+        };
+        index.extend()?;
+        Ok(index)
+    }
+
+    /// Indexes complete lines appended since the last scan. A trailing line
+    /// without a line break is left for the next scan, as a writer may still
+    /// be in the middle of it.
+    fn extend(&mut self) -> Result<()> {
+        let mut file = File::open(&self.text_path)?;
+        file.seek(SeekFrom::Start(self.indexed_len))?;
+        let mut reader = BufReader::new(file);
+        let mut buf = Vec::new();
+
+        loop {
+            buf.clear();
+            let read = reader.read_until(b'\n', &mut buf)?;
+            if read == 0 || !buf.ends_with(b"\n") {
+                break;
+            }
+
+            let line = String::from_utf8_lossy(&buf);
+            let line = line.trim_end_matches(['\n', '\r']);
+            // Lines before the first head line still form a record of their own
+            if self.offsets.is_empty() || is_log_head_line(line) {
+                self.offsets.push(self.indexed_len);
+                self.lines.push(self.line_count);
+            }
+            self.indexed_len += read as u64;
+            self.line_count += 1;
+        }
+        self.fingerprint = fingerprint(&self.text_path, self.indexed_len)?;
+        Ok(())
+    }
+
+    /// Whether the indexed part of the log is still what was indexed, so
+    /// the index may be extended instead of rebuilt.
+    fn is_prefix_of_log(&self) -> bool {
+        fingerprint(&self.text_path, self.indexed_len).ok() == Some(self.fingerprint)
+    }
+
+    /// Number of records covered by the index.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Number of complete lines covered by the index.
+    pub fn line_count(&self) -> u64 {
+        self.line_count
+    }
+
+    /// Byte offset and line number where record `record` starts, or the end
+    /// of the indexed part of the log if there is no such record yet.
+    pub fn position_of(&self, record: usize) -> (u64, u64) {
+        match (self.offsets.get(record), self.lines.get(record)) {
+            (Some(&offset), Some(&line)) => (offset, line),
+            _ => (self.indexed_len, self.line_count),
+        }
+    }
+
+    /// Reads records `start..end`, seeking straight to the first one.
+    pub fn read_records(&self, start: usize, end: usize) -> Result<Vec<LogRecord>> {
+        let end = end.min(self.len());
+        if start >= end {
+            return Ok(Vec::new());
+        }
+
+        let mut file = File::open(&self.text_path)?;
+        let from = self.offsets[start];
+        let to = match self.offsets.get(end) {
+            Some(&offset) => offset,
+            // The last record may have grown continuation lines since indexing
+            None => file.metadata()?.len(),
+        };
+
+        file.seek(SeekFrom::Start(from))?;
+        let mut bytes = Vec::with_capacity((to - from) as usize);
+        file.take(to - from).read_to_end(&mut bytes)?;
+
+        let mut records: Vec<LogRecord> =
+            LogRecordReader::starting_at(bytes.as_slice(), self.lines[start] as usize)
+                .collect::<std::io::Result<_>>()?;
+        records.truncate(end - start);
+        Ok(records)
+    }
+
+    /// Streams every record of the log from the beginning.
+    pub fn records(&self) -> Result<LogRecordReader<BufReader<File>>> {
+        Ok(LogRecordReader::new(BufReader::new(File::open(
+            &self.text_path,
+        )?)))
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        let tmp_path = path.with_extension(format!("idx.{}.tmp", std::process::id()));
+        {
+            let mut out = BufWriter::new(File::create(&tmp_path)?);
+            out.write_all(INDEX_MAGIC)?;
+            out.write_all(&[self.in_place as u8])?;
+            for value in [
+                self.stamp.len,
+                self.stamp.mtime_nanos,
+                self.indexed_len,
+                self.fingerprint,
+                self.line_count,
+                self.offsets.len() as u64,
+            ] {
+                out.write_all(&value.to_le_bytes())?;
+            }
+            let text_path = self.text_path.to_string_lossy();
+            out.write_all(&(text_path.len() as u64).to_le_bytes())?;
+            out.write_all(text_path.as_bytes())?;
+            for (offset, line) in self.offsets.iter().zip(&self.lines) {
+                out.write_all(&offset.to_le_bytes())?;
+                out.write_all(&line.to_le_bytes())?;
+            }
+            out.flush()?;
+        }
+        // Rename so concurrent readers never see a half-written index
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut input = BufReader::new(file);
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != INDEX_MAGIC {
+            bail!("Not a log index: {:?}", path);
+        }
+
+        let mut flag = [0u8; 1];
+        input.read_exact(&mut flag)?;
+        let mut read_u64 = || -> Result<u64> {
+            let mut bytes = [0u8; 8];
+            input.read_exact(&mut bytes)?;
+            Ok(u64::from_le_bytes(bytes))
+        };
+        let stamp = SourceStamp {
+            len: read_u64()?,
+            mtime_nanos: read_u64()?,
+        };
+        let indexed_len = read_u64()?;
+        let fingerprint = read_u64()?;
+        let line_count = read_u64()?;
+        let count = read_u64()?;
+        let path_len = read_u64()?;
+
+        // Sizes come from the file, so a damaged one must not make us
+        // allocate more than it holds
+        let header_len = INDEX_MAGIC.len() as u64 + 1 + 7 * 8;
+        let expected_len = count
+            .checked_mul(16)
+            .and_then(|entries| entries.checked_add(path_len))
+            .and_then(|body| body.checked_add(header_len));
+        if expected_len != Some(file_len) {
+            bail!("Log index {:?} is truncated or corrupt", path);
+        }
+        let (count, path_len) = (count as usize, path_len as usize);
+
+        let mut text_path = vec![0u8; path_len];
+        input.read_exact(&mut text_path)?;
+        let text_path = PathBuf::from(String::from_utf8(text_path)?);
+
+        let mut offsets = Vec::with_capacity(count);
+        let mut lines = Vec::with_capacity(count);
+        let mut entry = [0u8; 16];
+        for _ in 0..count {
+            input.read_exact(&mut entry)?;
+            offsets.push(u64::from_le_bytes(entry[..8].try_into()?));
+            lines.push(u64::from_le_bytes(entry[8..].try_into()?));
+        }
+
+        Ok(LogIndex {
+            stamp,
+            text_path,
+            in_place: flag[0] == 1,
+            indexed_len,
+            fingerprint,
+            line_count,
+            offsets,
+            lines,
+        })
+    }
+}
+
+/// Process-wide cache of log indexes, persisted under a cache directory and
+/// invalidated when a log's size or modification time changes.
+pub struct LogIndexCache {
+    dir: PathBuf,
+    entries: Mutex<HashMap<PathBuf, Arc<LogIndex>>>,
+    /// Held while a log's index is loaded, built or extended, so concurrent
+    /// requests for one log index it only once
+    building: Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>,
+}
+
+impl Default for LogIndexCache {
+    fn default() -> Self {
+        Self::new(default_cache_dir())
+    }
+}
+
+impl LogIndexCache {
+    pub fn new(dir: PathBuf) -> Self {
+        LogIndexCache {
+            dir,
+            entries: Mutex::new(HashMap::new()),
+            building: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns an up-to-date index for `path`, building, loading or extending
+    /// it as needed.
+    pub fn get(&self, path: &Path) -> Result<Arc<LogIndex>> {
+        let lock = self
+            .building
+            .lock()
+            .unwrap()
+            .entry(path.to_path_buf())
+            .or_default()
+            .clone();
+        let _building = lock.lock().unwrap();
+
+        let stamp = SourceStamp::of(path)?;
+        let index_path = self.cache_path(path, "idx");
+
+        let cached = self.entries.lock().unwrap().get(path).cloned();
+        let previous = cached.or_else(|| {
+            ensure_private_dir(&self.dir).ok()?;
+            LogIndex::load(&index_path)
+                .ok()
+                .filter(|index| index.in_place || index.text_path.exists())
+                .map(Arc::new)
+        });
+
+        let index = match previous {
+            Some(index) if index.stamp == stamp => index,
+            // Plain logs only ever grow until rotated, so index just the new
+            // tail, unless a larger file took the log's place
+            Some(index)
+                if index.in_place && stamp.len > index.stamp.len && index.is_prefix_of_log() =>
+            {
+                let mut index = Arc::unwrap_or_clone(index);
+                index.stamp = stamp;
+                index.extend()?;
+                self.persist(&index, &index_path);
+                Arc::new(index)
+            }
+            _ => {
+                let index = self.build(path, stamp)?;
+                self.persist(&index, &index_path);
+                Arc::new(index)
+            }
+        };
+
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), index.clone());
+        Ok(index)
+    }
+
+    fn build(&self, path: &Path, stamp: SourceStamp) -> Result<LogIndex> {
+        if detect_encoding(path)? == LogEncoding::Plain {
+            return LogIndex::build(path.to_path_buf(), true, stamp);
+        }
+
+        ensure_private_dir(&self.dir)?;
+        let text_path = self.cache_path(path, "log");
+        let tmp_path = text_path.with_extension(format!("log.{}.tmp", std::process::id()));
+        {
+            let mut out = BufWriter::new(File::create(&tmp_path)?);
+            decompress_to(path, &mut out)?;
+            out.flush()?;
+        }
+        fs::rename(&tmp_path, &text_path)?;
+        LogIndex::build(text_path, false, stamp)
+    }
+
+    /// Persisting is best effort: an unwritable cache dir only costs a rebuild
+    /// on the next start.
+    fn persist(&self, index: &LogIndex, index_path: &Path) {
+        if let Err(e) = ensure_private_dir(&self.dir) {
+            eprintln!("Not caching log index in {:?}: {}", self.dir, e);
+            return;
+        }
+        if let Err(e) = index.save(index_path) {
+            eprintln!("Failed to save log index {:?}: {}", index_path, e);
+        }
+    }
+
+    fn cache_path(&self, path: &Path, extension: &str) -> PathBuf {
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let hash = fnv1a(FNV_OFFSET_BASIS, canonical.as_os_str().as_encoded_bytes());
+        let stem = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.dir
+            .join(format!("{}-{:016x}.{}", stem, hash, extension))
+    }
+}
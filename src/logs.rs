@@ -0,0 +1,314 @@
+use crate::models::{LogLevel, LogRecord};
+use anyhow::Result;
+use flate2::read::MultiGzDecoder;
+use regex::Regex;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// On-disk storage of a log file. MASQ rotates logs into `_r000NN.log.zip`
+/// files that are actually gzip streams, but hand-made bundles sometimes
+/// contain real ZIP containers, so the format is sniffed from the magic bytes
+/// rather than trusted from the extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogEncoding {
+    Plain,
+    Gzip,
+    Zip,
+}
+
+pub fn detect_encoding(path: &Path) -> Result<LogEncoding> {
+    let mut magic = [0u8; 4];
+    let mut file = File::open(path)?;
+    let read = file.read(&mut magic)?;
+
+    Ok(match &magic[..read] {
+        [0x1f, 0x8b, ..] => LogEncoding::Gzip,
+        [b'P', b'K', 0x03, 0x04] => LogEncoding::Zip,
+        _ => LogEncoding::Plain,
+    })
+}
+
+/// Writes the decompressed content of a log file to `out`, streaming gzip
+/// and ZIP archives instead of buffering them. ZIP containers have all their
+/// file entries concatenated in archive order.
+pub fn decompress_to(path: &Path, out: &mut impl Write) -> Result<()> {
+    match detect_encoding(path)? {
+        LogEncoding::Plain => {
+            io::copy(&mut File::open(path)?, out)?;
+        }
+        LogEncoding::Gzip => {
+            io::copy(&mut MultiGzDecoder::new(File::open(path)?), out)?;
+        }
+        LogEncoding::Zip => {
+            let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+            let mut ends_with_newline = true;
+            for i in 0..archive.len() {
+                let mut entry = archive.by_index(i)?;
+                if entry.is_dir() || entry.size() == 0 {
+                    continue;
+                }
+                if !ends_with_newline {
+                    out.write_all(b"\n")?;
+                }
+                let mut content = Vec::with_capacity(entry.size() as usize);
+                entry.read_to_end(&mut content)?;
+                ends_with_newline = content.ends_with(b"\n");
+                out.write_all(&content)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads a log file as text, transparently decompressing gzip and ZIP archives.
+/// Invalid UTF-8 is replaced rather than rejected.
+pub fn read_log_text(path: &Path) -> Result<String> {
+    let mut bytes = Vec::new();
+    decompress_to(path, &mut bytes)?;
+
+    Ok(match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+    })
+}
+
+/// Name under which a (possibly archived) log is served as plain text,
+/// e.g. `MASQNode_r00000.log.zip` -> `MASQNode_r00000.log`.
+pub fn text_file_name(file_name: &str) -> &str {
+    file_name
+        .strip_suffix(".zip")
+        .or_else(|| file_name.strip_suffix(".gz"))
+        .unwrap_or(file_name)
+}
+
+/// Turns an entry of `NodeData::log_files` into a path on disk.
+pub fn log_file_path(input_dir: &Path, node_name: &str, stored: &str) -> PathBuf {
+    let path = PathBuf::from(stored);
+    if path.parent().is_some_and(|p| p != Path::new("")) {
+        // It's a path with directory components (flat structure)
+        path
+    } else {
+        // It's just a filename (nested structure), construct full path
+        input_dir.join(node_name).join(stored)
+    }
+}
+
+/// Server-side predicate over log records.
+///
+/// An empty filter matches everything. Records without a parsed head line
+/// (e.g. the startup banner) only pass filters that constrain nothing but the
+/// text pattern.
+#[derive(Debug, Default, Clone)]
+pub struct LogFilter {
+    pub min_level: Option<LogLevel>,
+    /// Allow list; a record passes if its component matches any entry
+    pub components: Vec<String>,
+    /// Deny list; a record is dropped if its component matches any entry
+    pub exclude_components: Vec<String>,
+    pub thread: Option<String>,
+    /// Matched against the full text of the record, continuation lines included
+    pub pattern: Option<Regex>,
+    /// Inclusive lower bound, compared against the `YYYY-MM-DD HH:MM:SS.mmm` timestamp
+    pub since: Option<String>,
+    /// Inclusive upper bound; a prefix such as `2025-02-03 20:00` covers the whole minute
+    pub until: Option<String>,
+}
+
+impl LogFilter {
+    pub fn is_empty(&self) -> bool {
+        self.min_level.is_none()
+            && self.components.is_empty()
+            && self.exclude_components.is_empty()
+            && self.thread.is_none()
+            && self.pattern.is_none()
+            && self.since.is_none()
+            && self.until.is_none()
+    }
+
+    fn constrains_fields(&self) -> bool {
+        self.min_level.is_some()
+            || !self.components.is_empty()
+            || !self.exclude_components.is_empty()
+            || self.thread.is_some()
+            || self.since.is_some()
+            || self.until.is_some()
+    }
+
+    pub fn matches(&self, record: &LogRecord) -> bool {
+        if self.constrains_fields() && !self.matches_fields(record) {
+            return false;
+        }
+
+        match &self.pattern {
+            Some(pattern) => pattern.is_match(&record.to_text()),
+            None => true,
+        }
+    }
+
+    fn matches_fields(&self, record: &LogRecord) -> bool {
+        let (Some(timestamp), Some(thread), Some(level), Some(component)) = (
+            &record.timestamp,
+            &record.thread,
+            &record.level,
+            &record.component,
+        ) else {
+            return false;
+        };
+
+        if self.min_level.is_some_and(|min| *level < min) {
+            return false;
+        }
+        if !self.components.is_empty()
+            && !self
+                .components
+                .iter()
+                .any(|name| component_matches(component, name))
+        {
+            return false;
+        }
+        if self
+            .exclude_components
+            .iter()
+            .any(|name| component_matches(component, name))
+        {
+            return false;
+        }
+        if self.thread.as_ref().is_some_and(|t| t != thread) {
+            return false;
+        }
+        if self
+            .since
+            .as_ref()
+            .is_some_and(|since| timestamp.as_str() < since.as_str())
+        {
+            return false;
+        }
+        if self.until.as_ref().is_some_and(|until| {
+            timestamp.as_str() > until.as_str() && !timestamp.starts_with(until.as_str())
+        }) {
+            return false;
+        }
+        true
+    }
+}
+
+/// A component filter entry matches the component itself and any `::` child,
+/// so `hyper` covers `hyper::buffer` and `hyper::http::h1`.
+pub fn component_matches(component: &str, name: &str) -> bool {
+    component == name
+        || component
+            .strip_prefix(name)
+            .is_some_and(|rest| rest.starts_with("::"))
+}
+
+/// Normalizes a user-supplied timestamp bound to the log's
+/// `YYYY-MM-DD HH:MM:SS.mmm` layout (accepts the `T` separator used by
+/// HTML `datetime-local` inputs).
+pub fn normalize_timestamp_bound(value: &str) -> String {
+    value.trim().replacen('T', " ", 1)
+}
+
+/// Milliseconds since the Unix epoch of a log timestamp
+/// (`YYYY-MM-DD HH:MM:SS.mmm`, read as UTC), for measuring delays between
+/// records.
+pub fn timestamp_millis(timestamp: &str) -> Option<i64> {
+    let (date, time) = timestamp.trim().split_once([' ', 'T'])?;
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let (time, millis) = time.split_once('.').unwrap_or((time, "0"));
+    let mut time = time.splitn(3, ':').map(str::parse::<i64>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+    let millis: i64 = millis.get(..3).unwrap_or(millis).parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Days from civil date, after Howard Hinnant's algorithm
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    Some(((days * 24 + hour) * 60 + minute) * 60_000 + second * 1000 + millis)
+}
+
+/// Seconds since the Unix epoch in the log's `YYYY-MM-DD HH:MM:SS` layout,
+/// as UTC.
+pub fn format_unix_seconds(seconds: i64) -> String {
+    let days = seconds.div_euclid(86_400);
+    let time = seconds.rem_euclid(86_400);
+
+    // Civil date from days, after Howard Hinnant's algorithm
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Start and end of a page of `num_records` records out of `total`.
+pub fn page_bounds(
+    total: usize,
+    start: Option<usize>,
+    num_records: usize,
+    from_end: bool,
+) -> (usize, usize) {
+    if from_end {
+        (total.saturating_sub(num_records), total)
+    } else {
+        let start = start.unwrap_or(0);
+        (start, start.saturating_add(num_records).min(total))
+    }
+}
+
+/// Filters a stream in one pass, counting every match but keeping only the
+/// requested page. Returns the page, the number of matches and the page bounds.
+pub fn filter_page<T>(
+    items: impl Iterator<Item = T>,
+    matches: impl Fn(&T) -> bool,
+    start: Option<usize>,
+    num_records: usize,
+    from_end: bool,
+) -> (Vec<T>, usize, usize, usize) {
+    let mut page = VecDeque::new();
+    let mut total = 0;
+    let first = start.unwrap_or(0);
+
+    for item in items.filter(|item| matches(item)) {
+        if from_end {
+            page.push_back(item);
+            if page.len() > num_records {
+                page.pop_front();
+            }
+        } else if total >= first && total < first.saturating_add(num_records) {
+            page.push_back(item);
+        }
+        total += 1;
+    }
+
+    let (start, end) = page_bounds(total, start, num_records, from_end);
+    (page.into(), total, start, end)
+}
@@ -1,12 +1,18 @@
-mod models;
-mod parser;
-mod routes;
-
-use axum::{routing::get, Router};
-use clap::Parser;
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use clap::{Parser, Subcommand};
+use masq_log_visualizer::export::{entry_range, write_entries, ExportFormat};
+use masq_log_visualizer::identity::IdentityRegistry;
+use masq_log_visualizer::log_index::{default_cache_dir, LogIndexCache};
+use masq_log_visualizer::redact::Redactor;
+use masq_log_visualizer::watcher::{spawn_watcher, NodeWatcher};
+use masq_log_visualizer::{parser, routes};
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use tera::Tera;
 use tower_http::cors::CorsLayer;
 use tower_http::services::ServeDir;
@@ -26,6 +32,54 @@ struct Args {
     /// Server host
     #[arg(long, default_value = "127.0.0.1")]
     host: String,
+
+    /// Directory for log indexes and decompressed archives, readable by
+    /// its owner only (defaults to a folder in the user's cache directory)
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+
+    /// Seconds between checks of the input directory for new or changed
+    /// files (0 disables watching)
+    #[arg(long, default_value_t = 2)]
+    watch_interval: u64,
+
+    /// Serve passwords, API keys and wallet secrets unmasked
+    #[arg(long)]
+    no_redact: bool,
+
+    /// Extra regex of secrets to mask in configs, logs and databases; if it
+    /// has a group named `secret` only that part is masked (repeatable)
+    #[arg(long = "redact-pattern", value_name = "REGEX")]
+    redact_patterns: Vec<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Write gossip entries of a node to files instead of starting the server
+    Export {
+        /// Node whose gossip to export
+        #[arg(long)]
+        node: String,
+
+        /// First entry to export (defaults to the first)
+        #[arg(long)]
+        from: Option<usize>,
+
+        /// Last entry to export (defaults to the last)
+        #[arg(long)]
+        to: Option<usize>,
+
+        /// dot, graphml or svg
+        #[arg(long, default_value = "svg")]
+        format: ExportFormat,
+
+        /// Directory to write the files to
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+    },
 }
 
 #[tokio::main]
@@ -41,10 +95,47 @@ async fn main() -> anyhow::Result<()> {
         std::process::exit(1);
     }
 
+    let redactor = if args.no_redact {
+        Redactor::disabled()
+    } else {
+        match Redactor::new(&args.redact_patterns) {
+            Ok(redactor) => redactor,
+            Err(e) => {
+                eprintln!("Invalid redaction pattern: {}", e);
+                std::process::exit(1);
+            }
+        }
+    };
+
+    // Taken before the scan, so files that change while it runs are looked
+    // at again on the first poll
+    let mut watcher = NodeWatcher::new(input_dir.clone());
+
     println!("Scanning input directory: {:?}", input_dir);
     let nodes_data = parser::scan_directory(&input_dir)?;
+    watcher.start_from(&nodes_data);
     println!("Found {} nodes.", nodes_data.len());
 
+    if let Some(Command::Export {
+        node,
+        from,
+        to,
+        format,
+        output,
+    }) = args.command
+    {
+        let Some(node_data) = nodes_data.get(&node) else {
+            eprintln!("Node not found: {}", node);
+            std::process::exit(1);
+        };
+        let registry = IdentityRegistry::build(&nodes_data);
+        let range = entry_range(node_data, from, to)?;
+        for path in write_entries(node_data, range, format, &registry, &output)? {
+            println!("Wrote {:?}", path);
+        }
+        return Ok(());
+    }
+
     // Initialize Tera templates
     // Find templates directory relative to executable location
     let exe_path = std::env::current_exe()?;
@@ -67,12 +158,27 @@ async fn main() -> anyhow::Result<()> {
         }
     };
 
+    let identities = IdentityRegistry::build(&nodes_data);
     let app_state = routes::AppState {
         nodes_data: Arc::new(RwLock::new(nodes_data)),
         input_dir: input_dir.clone(),
         tera: Arc::new(tera),
+        log_index: Arc::new(LogIndexCache::new(
+            args.cache_dir.unwrap_or_else(default_cache_dir),
+        )),
+        redactor: Arc::new(redactor),
+        identities: Arc::new(RwLock::new(identities)),
     };
 
+    if args.watch_interval > 0 {
+        spawn_watcher(
+            watcher,
+            app_state.nodes_data.clone(),
+            app_state.identities.clone(),
+            Duration::from_secs(args.watch_interval),
+        );
+    }
+
     // Setup Router
     // Determine assets directory (relative to exe or CWD)
     let assets_dir = exe_dir.join("assets");
@@ -85,17 +191,64 @@ async fn main() -> anyhow::Result<()> {
     let app = Router::new()
         .route("/", get(routes::dashboard))
         .route("/node/:node_name", get(routes::node_view))
+        .route("/timeline", get(routes::timeline_view))
+        .route("/network", get(routes::network_view))
+        .route("/accounting", get(routes::accounting_view))
+        .route("/api/timeline", get(routes::get_timeline))
+        .route(
+            "/api/logs/:node_name/:file_name/tail",
+            get(routes::tail_log),
+        )
         .route(
             "/api/logs/:node_name/:file_name/range",
             get(routes::get_log_range),
         )
         .route("/api/logs/:node_name/:file_name", get(routes::download_log))
         .route("/api/db/:node_name", get(routes::get_db_tables))
+        .route("/api/db/:node_name/query", post(routes::post_db_query))
         .route(
             "/api/db/:node_name/:table_name",
             get(routes::get_db_table_data),
         )
         .route("/api/gossip/:node_name", get(routes::get_gossip))
+        .route("/api/gossip/:node_name/diff", get(routes::get_gossip_diff))
+        .route(
+            "/api/gossip/:node_name/events",
+            get(routes::get_gossip_events),
+        )
+        .route(
+            "/api/gossip/:node_name/metrics",
+            get(routes::get_gossip_metrics_series),
+        )
+        .route(
+            "/api/gossip/:node_name/export",
+            get(routes::get_gossip_export_range),
+        )
+        .route(
+            "/api/gossip/:node_name/:index/graph",
+            get(routes::get_gossip_graph),
+        )
+        .route(
+            "/api/gossip/:node_name/:index/metrics",
+            get(routes::get_gossip_metrics),
+        )
+        .route(
+            "/api/gossip/:node_name/:index/export",
+            get(routes::get_gossip_export),
+        )
+        .route("/api/routes/:node_name", get(routes::get_routes))
+        .route("/api/config/:node_name", get(routes::get_config))
+        .route(
+            "/api/network/propagation",
+            get(routes::get_gossip_propagation),
+        )
+        .route(
+            "/api/network/convergence",
+            get(routes::get_gossip_convergence),
+        )
+        .route("/api/network/topology", get(routes::get_network_topology))
+        .route("/api/identities", get(routes::get_identities))
+        .route("/api/accounting", get(routes::get_accounting))
         .nest_service("/assets", ServeDir::new(assets_path))
         .layer(CorsLayer::permissive())
         .layer(TraceLayer::new_for_http())
@@ -0,0 +1,191 @@
+use crate::logs::format_unix_seconds;
+use rusqlite::types::ValueRef;
+use serde::Serialize;
+use std::fmt::Write;
+use std::ops::RangeInclusive;
+
+/// `schema_version`s of `node-data.db` whose layout is described by
+/// `KNOWN_TABLES`: amounts are wei, split into a high and a low column since
+/// they do not fit SQLite integers.
+pub const KNOWN_SCHEMA_VERSIONS: RangeInclusive<u32> = 7..=10;
+
+/// Decimal places of the MASQ token.
+const MASQ_DECIMALS: usize = 18;
+
+/// Bytes of a BLOB shown in its hex dump.
+const HEXDUMP_LIMIT: usize = 4096;
+
+/// What a column of a known MASQ table holds.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ColumnKind {
+    /// Upper bits of a wei amount: `high * 2^63 + low`
+    WeiHigh,
+    /// Lower 63 bits of a wei amount
+    WeiLow,
+    /// Seconds since the Unix epoch
+    Timestamp,
+    Wallet,
+    TxHash,
+}
+
+/// Every column of a known table, with what it holds if it is typed.
+type KnownColumns = &'static [(&'static str, Option<ColumnKind>)];
+
+/// Typed columns of the tables of the known schemas. Tables whose columns
+/// differ are rendered generically.
+const KNOWN_TABLES: [(&str, KnownColumns); 4] = [
+    (
+        "payable",
+        &[
+            ("wallet_address", Some(ColumnKind::Wallet)),
+            ("balance_high_b", Some(ColumnKind::WeiHigh)),
+            ("balance_low_b", Some(ColumnKind::WeiLow)),
+            ("last_paid_timestamp", Some(ColumnKind::Timestamp)),
+            ("pending_payable_rowid", None),
+        ],
+    ),
+    (
+        "receivable",
+        &[
+            ("wallet_address", Some(ColumnKind::Wallet)),
+            ("balance_high_b", Some(ColumnKind::WeiHigh)),
+            ("balance_low_b", Some(ColumnKind::WeiLow)),
+            ("last_received_timestamp", Some(ColumnKind::Timestamp)),
+        ],
+    ),
+    (
+        "pending_payable",
+        &[
+            ("rowid", None),
+            ("transaction_hash", Some(ColumnKind::TxHash)),
+            ("amount_high_b", Some(ColumnKind::WeiHigh)),
+            ("amount_low_b", Some(ColumnKind::WeiLow)),
+            ("payable_timestamp", Some(ColumnKind::Timestamp)),
+            ("attempt", None),
+            ("process_error", None),
+        ],
+    ),
+    ("banned", &[("wallet_address", Some(ColumnKind::Wallet))]),
+];
+
+/// How the columns of a table render. `None` if the schema version or the
+/// table is not known, in which case the table is shown generically.
+pub fn table_layout(
+    schema_version: Option<u32>,
+    table_name: &str,
+    columns: &[String],
+) -> Option<TableLayout> {
+    if !KNOWN_SCHEMA_VERSIONS.contains(&schema_version?) {
+        return None;
+    }
+    let kinds = match KNOWN_TABLES.iter().find(|(name, _)| *name == table_name) {
+        Some((_, known)) => {
+            let matches = known.len() == columns.len()
+                && known
+                    .iter()
+                    .all(|(name, _)| columns.iter().any(|c| c == name));
+            if !matches {
+                return None;
+            }
+            columns
+                .iter()
+                .map(|c| known.iter().find(|(name, _)| name == c).and_then(|k| k.1))
+                .collect()
+        }
+        // Other tables of a known schema only get their BLOBs dumped
+        None => vec![None; columns.len()],
+    };
+    Some(TableLayout { kinds })
+}
+
+/// Kinds of the columns of a table of a known schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableLayout {
+    pub kinds: Vec<Option<ColumnKind>>,
+}
+
+impl TableLayout {
+    /// Human-readable rendering of each cell of a row, `None` where the raw
+    /// value reads as well.
+    pub fn render_row(&self, row: &[ValueRef]) -> Vec<Option<String>> {
+        let partner = |kind| self.kinds.iter().position(|k| *k == Some(kind));
+        let wei = || {
+            let high = row.get(partner(ColumnKind::WeiHigh)?)?.as_i64().ok()?;
+            let low = row.get(partner(ColumnKind::WeiLow)?)?.as_i64().ok()?;
+            Some(join_wei(high, low))
+        };
+        row.iter()
+            .zip(&self.kinds)
+            .map(|(value, kind)| match (kind, value) {
+                (_, ValueRef::Null) => None,
+                (_, ValueRef::Blob(bytes)) => Some(hexdump(bytes)),
+                (Some(ColumnKind::WeiHigh), _) => wei().map(format_masq),
+                (Some(ColumnKind::WeiLow), _) => wei().map(|wei| format!("{} wei", wei)),
+                (Some(ColumnKind::Timestamp), ValueRef::Integer(seconds)) => {
+                    Some(format!("{} UTC", format_unix_seconds(*seconds)))
+                }
+                (Some(ColumnKind::Wallet | ColumnKind::TxHash), ValueRef::Text(text)) => {
+                    let text = String::from_utf8_lossy(text);
+                    let hex = text.trim().trim_start_matches("0x").to_lowercase();
+                    Some(format!("0x{}", hex))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// The amount MASQ splits into `high * 2^63 + low` columns.
+pub fn join_wei(high: i64, low: i64) -> i128 {
+    (i128::from(high) << 63) + i128::from(low)
+}
+
+/// `wei` in MASQ tokens, without trailing zeros.
+pub fn format_masq(wei: i128) -> String {
+    let digits = wei.unsigned_abs().to_string();
+    let digits = format!("{:0>width$}", digits, width = MASQ_DECIMALS + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - MASQ_DECIMALS);
+    let fraction = fraction.trim_end_matches('0');
+    let sign = if wei < 0 { "-" } else { "" };
+    if fraction.is_empty() {
+        format!("{}{} MASQ", sign, whole)
+    } else {
+        format!("{}{}.{} MASQ", sign, whole, fraction)
+    }
+}
+
+/// Length and hex dump of a BLOB, sixteen bytes a line with their ASCII.
+pub fn hexdump(bytes: &[u8]) -> String {
+    let mut dump = format!("{} bytes", bytes.len());
+    for (line, chunk) in bytes[..bytes.len().min(HEXDUMP_LIMIT)]
+        .chunks(16)
+        .enumerate()
+    {
+        let _ = write!(dump, "\n{:08x} ", line * 16);
+        for byte in chunk {
+            let _ = write!(dump, " {:02x}", byte);
+        }
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        let _ = write!(
+            dump,
+            "{:pad$}  |{}|",
+            "",
+            ascii,
+            pad = (16 - chunk.len()) * 3
+        );
+    }
+    if bytes.len() > HEXDUMP_LIMIT {
+        dump.push_str("\n...");
+    }
+    dump
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::str::FromStr;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GossipEntry {
@@ -9,10 +11,377 @@ pub struct GossipEntry {
     pub dot: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct NeighborhoodEdge {
+/// What a gossip-related log line without a graph reports.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GossipEventKind {
+    /// A node announcing itself to its first neighbor
+    Debut,
+    /// A Debut handed on to a node better placed to accept it
+    Pass,
+    /// A node introducing the debuting node to one of its neighbors
+    Introduction,
+    /// Gossip that was refused, or its sender banned
+    Rejection,
+    /// A `GossipFailure` message sent or received
+    GossipFailure,
+}
+
+/// A step of the gossip lifecycle that carries no graph, such as a rejected
+/// Debut or a `GossipFailure`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GossipEvent {
+    pub timestamp: String,
+    pub actor: String,
+    pub kind: GossipEventKind,
+    /// Why the gossip was rejected or failed, as far as the line says
+    pub reason: Option<String>,
+    pub message: String,
+}
+
+/// A node of a gossip dump. MASQ labels nodes with up to three lines:
+/// `AR v1 US` (accepts connections, routes data, version, country; lowercase
+/// letters for a cleared flag), the abbreviated public key, and the node
+/// address `ip:port/port`. Fields stay empty for lines a dump leaves out.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GossipNode {
+    /// The full public key the node is identified by in the dump
+    pub public_key: String,
+    pub version: Option<u32>,
+    pub country_code: Option<String>,
+    pub accepts_connections: Option<bool>,
+    pub routes_data: Option<bool>,
+    pub ip: Option<String>,
+    pub ports: Vec<u16>,
+    pub rate_pack: Option<RatePack>,
+    /// Drawn filled: the node whose database was dumped
+    pub known_source: bool,
+    /// Drawn as a box: the node the gossip was addressed to
+    pub known_target: bool,
+    /// False for nodes that only appear as the end of an edge
+    pub declared: bool,
+    /// Label lines, as drawn
+    pub label: Vec<String>,
+    /// All DOT attributes as written
+    pub attributes: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GossipEdge {
     pub from: String,
     pub to: String,
+    pub attributes: BTreeMap<String, String>,
+}
+
+/// A `digraph db { ... }` gossip dump parsed into nodes and edges, in order
+/// of first appearance.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct GossipGraph {
+    pub nodes: Vec<GossipNode>,
+    pub edges: Vec<GossipEdge>,
+}
+
+/// A typed attribute of a gossip node that differs between two snapshots.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct NodeAttributeChange {
+    pub field: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GossipNodeChange {
+    pub public_key: String,
+    pub changes: Vec<NodeAttributeChange>,
+}
+
+/// What changed from one gossip snapshot to a later one.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GossipDiff {
+    pub added_nodes: Vec<GossipNode>,
+    pub removed_nodes: Vec<GossipNode>,
+    pub changed_nodes: Vec<GossipNodeChange>,
+    pub added_edges: Vec<GossipEdge>,
+    pub removed_edges: Vec<GossipEdge>,
+}
+
+impl GossipDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.changed_nodes.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+    }
+}
+
+impl GossipNode {
+    /// The typed attributes compared between snapshots, as display strings.
+    fn comparable_attributes(&self) -> [(&'static str, Option<String>); 7] {
+        [
+            ("version", self.version.map(|v| v.to_string())),
+            ("countryCode", self.country_code.clone()),
+            (
+                "acceptsConnections",
+                self.accepts_connections.map(|v| v.to_string()),
+            ),
+            ("routesData", self.routes_data.map(|v| v.to_string())),
+            ("ip", self.ip.clone()),
+            (
+                "ports",
+                Some(self.ports.iter().map(u16::to_string).collect::<Vec<_>>())
+                    .filter(|ports| !ports.is_empty())
+                    .map(|ports| ports.join("/")),
+            ),
+            (
+                "ratePack",
+                self.rate_pack.map(|r| {
+                    format!(
+                        "{}|{}|{}|{}",
+                        r.routing_byte_rate,
+                        r.routing_service_rate,
+                        r.exit_byte_rate,
+                        r.exit_service_rate
+                    )
+                }),
+            ),
+        ]
+    }
+}
+
+impl GossipGraph {
+    pub fn node(&self, public_key: &str) -> Option<&GossipNode> {
+        self.nodes.iter().find(|n| n.public_key == public_key)
+    }
+
+    /// Nodes and edges added, removed or changed in `newer` compared to
+    /// this graph. Edges are matched by their ends only.
+    pub fn diff(&self, newer: &GossipGraph) -> GossipDiff {
+        let old_nodes: HashMap<&str, &GossipNode> = self
+            .nodes
+            .iter()
+            .map(|n| (n.public_key.as_str(), n))
+            .collect();
+        let new_nodes: HashMap<&str, &GossipNode> = newer
+            .nodes
+            .iter()
+            .map(|n| (n.public_key.as_str(), n))
+            .collect();
+        let old_edges: BTreeSet<(&str, &str)> = self
+            .edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str()))
+            .collect();
+        let new_edges: BTreeSet<(&str, &str)> = newer
+            .edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str()))
+            .collect();
+
+        let mut diff = GossipDiff::default();
+        for node in &newer.nodes {
+            match old_nodes.get(node.public_key.as_str()) {
+                None => diff.added_nodes.push(node.clone()),
+                Some(old) => {
+                    let changes: Vec<NodeAttributeChange> = old
+                        .comparable_attributes()
+                        .into_iter()
+                        .zip(node.comparable_attributes())
+                        .filter(|((_, old), (_, new))| old != new)
+                        .map(|((field, old), (_, new))| NodeAttributeChange {
+                            field: field.to_string(),
+                            old,
+                            new,
+                        })
+                        .collect();
+                    if !changes.is_empty() {
+                        diff.changed_nodes.push(GossipNodeChange {
+                            public_key: node.public_key.clone(),
+                            changes,
+                        });
+                    }
+                }
+            }
+        }
+        diff.removed_nodes = self
+            .nodes
+            .iter()
+            .filter(|n| !new_nodes.contains_key(n.public_key.as_str()))
+            .cloned()
+            .collect();
+        diff.added_edges = newer
+            .edges
+            .iter()
+            .filter(|e| !old_edges.contains(&(e.from.as_str(), e.to.as_str())))
+            .cloned()
+            .collect();
+        diff.removed_edges = self
+            .edges
+            .iter()
+            .filter(|e| !new_edges.contains(&(e.from.as_str(), e.to.as_str())))
+            .cloned()
+            .collect();
+        diff
+    }
+}
+
+/// Severity of a MASQ log line, ordered from most to least verbose.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Trace => "TRACE",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "TRACE" => Ok(LogLevel::Trace),
+            "DEBUG" => Ok(LogLevel::Debug),
+            "INFO" => Ok(LogLevel::Info),
+            "WARN" | "WARNING" => Ok(LogLevel::Warn),
+            "ERROR" => Ok(LogLevel::Error),
+            other => Err(format!("Unknown log level: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A single logical entry of a MASQ node log.
+///
+/// A head line looks like
+/// `2025-02-03 20:00:46.347 Thd9: DEBUG: Neighborhood: Connecting to persistent database`.
+/// Lines that do not start a new entry (multi-line payloads, the startup banner)
+/// are collected in `continuation`. Lines preceding the first head line form a
+/// record whose structured fields are all `None`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LogRecord {
+    /// Zero-based line number of the head line within its file
+    pub line: usize,
+    pub timestamp: Option<String>,
+    pub thread: Option<String>,
+    pub level: Option<LogLevel>,
+    pub component: Option<String>,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub continuation: Vec<String>,
+}
+
+impl LogRecord {
+    /// Reassembles the original text of the record, continuation lines included.
+    pub fn to_text(&self) -> String {
+        let mut text = match (&self.timestamp, &self.thread, &self.level, &self.component) {
+            (Some(ts), Some(thread), Some(level), Some(component)) => {
+                format!(
+                    "{} {}: {}: {}: {}",
+                    ts, thread, level, component, self.message
+                )
+            }
+            _ => self.message.clone(),
+        };
+        for line in &self.continuation {
+            text.push('\n');
+            text.push_str(line);
+        }
+        text
+    }
+}
+
+/// A log record on the merged multi-node timeline, tagged with its origin.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct TimelineRecord {
+    pub node: String,
+    pub color: String,
+    /// Log file the record came from
+    pub file: String,
+    #[serde(flatten)]
+    pub record: LogRecord,
+}
+
+/// Which leg of a round trip a route was logged for.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RouteDirection {
+    /// `Route over:` from the originating node towards the exit
+    Over,
+    /// `Route back:` from the exit to the originating node
+    Back,
+}
+
+/// One route a node logged, with every hop in order.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RouteRecord {
+    pub timestamp: String,
+    pub direction: RouteDirection,
+    pub hops: Vec<String>,
+}
+
+/// How often, and when, routes went from one hop straight to another.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteEdgeCount {
+    pub from: String,
+    pub to: String,
+    pub count: usize,
+    pub first_used: String,
+    pub last_used: String,
+}
+
+impl RouteEdgeCount {
+    /// Counts the consecutive hops of `routes`, most used first.
+    pub fn aggregate<'a>(routes: impl IntoIterator<Item = &'a RouteRecord>) -> Vec<Self> {
+        let mut edges: HashMap<(&str, &str), RouteEdgeCount> = HashMap::new();
+        for route in routes {
+            for hop in route.hops.windows(2) {
+                let edge = edges
+                    .entry((&hop[0], &hop[1]))
+                    .or_insert_with(|| RouteEdgeCount {
+                        from: hop[0].clone(),
+                        to: hop[1].clone(),
+                        count: 0,
+                        first_used: route.timestamp.clone(),
+                        last_used: route.timestamp.clone(),
+                    });
+                edge.count += 1;
+                if route.timestamp < edge.first_used {
+                    edge.first_used = route.timestamp.clone();
+                }
+                if route.timestamp > edge.last_used {
+                    edge.last_used = route.timestamp.clone();
+                }
+            }
+        }
+        let mut edges: Vec<RouteEdgeCount> = edges.into_values().collect();
+        edges.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| (&a.from, &a.to).cmp(&(&b.from, &b.to)))
+        });
+        edges
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -30,11 +399,102 @@ pub struct DatabaseData {
     pub tables: HashMap<String, TableData>,
 }
 
+/// `rate-pack` setting: what a node charges for routing and exiting traffic.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RatePack {
+    pub routing_byte_rate: u64,
+    pub routing_service_rate: u64,
+    pub exit_byte_rate: u64,
+    pub exit_service_rate: u64,
+}
+
+/// `payment-thresholds` setting: when a node expects debts to be paid.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentThresholds {
+    pub debt_threshold_gwei: u64,
+    pub maturity_threshold_sec: u64,
+    pub payment_grace_period_sec: u64,
+    pub permanent_debt_allowed_gwei: u64,
+    pub threshold_interval_sec: u64,
+    pub unban_below_gwei: u64,
+}
+
+/// Settings of a node as written in `config.toml` (or `config.del`).
+///
+/// The commonly inspected settings are typed; `entries` keeps every setting
+/// verbatim, including unknown ones and values that failed to parse.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeConfig {
+    pub chain: Option<String>,
+    pub ip: Option<String>,
+    pub clandestine_port: Option<u16>,
+    pub neighborhood_mode: Option<String>,
+    pub rate_pack: Option<RatePack>,
+    pub payment_thresholds: Option<PaymentThresholds>,
+    pub neighbors: Vec<String>,
+    pub dns_servers: Vec<String>,
+    pub log_level: Option<String>,
+    pub entries: BTreeMap<String, String>,
+}
+
+/// A node descriptor as used in `neighbors`:
+/// `masq://<chain>:<public key>@<ip>:<port>[/<port>...]`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeDescriptor {
+    pub chain: String,
+    pub public_key: String,
+    /// Absent for descriptors of nodes that cannot be reached directly
+    pub ip: Option<String>,
+    pub ports: Vec<u16>,
+}
+
+/// One setting whose value differs between two configs.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ConfigDifference {
+    pub key: String,
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+impl NodeConfig {
+    /// Settings that are missing from one side or have different values.
+    pub fn diff(&self, other: &NodeConfig) -> Vec<ConfigDifference> {
+        let keys: BTreeSet<&String> = self.entries.keys().chain(other.entries.keys()).collect();
+        keys.into_iter()
+            .filter_map(|key| {
+                let left = self.entries.get(key);
+                let right = other.entries.get(key);
+                (left != right).then(|| ConfigDifference {
+                    key: key.clone(),
+                    left: left.cloned(),
+                    right: right.cloned(),
+                })
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct NodeData {
     pub name: String,
-    pub neighborhood: Vec<NeighborhoodEdge>,
+    /// Every route logged, in log order
+    pub routes: Vec<RouteRecord>,
     pub gossip: Vec<GossipEntry>,
+    #[serde(rename = "gossipEvents")]
+    pub gossip_events: Vec<GossipEvent>,
+    /// Descriptors the node logged for itself at startup, one per key it ran with
+    #[serde(rename = "localDescriptors")]
+    pub local_descriptors: Vec<NodeDescriptor>,
+    /// Earning wallets the node logged, one per wallet it ran with
+    #[serde(rename = "earningWallets")]
+    pub earning_wallets: Vec<String>,
+    /// Consuming wallets the node logged, one per wallet it ran with
+    #[serde(rename = "consumingWallets")]
+    pub consuming_wallets: Vec<String>,
     #[serde(rename = "logFiles")]
     pub log_files: Vec<String>,
     #[serde(rename = "currentLog")]
@@ -43,6 +503,25 @@ pub struct NodeData {
     // but for compatibility with the template rendering which expects `node.database.tables`,
     // we should include it.
     pub database: DatabaseData,
+    /// Settings from `config.toml`
+    pub config: Option<NodeConfig>,
+    /// Settings from `config.del`
+    #[serde(rename = "configDel")]
+    pub config_del: Option<NodeConfig>,
+    /// Bytes of `MASQNode_rCURRENT.log` routes and gossip were parsed from,
+    /// where the watcher carries on
+    #[serde(skip)]
+    pub current_log_parsed: u64,
+}
+
+impl NodeData {
+    /// A node with nothing parsed yet.
+    pub fn new(name: impl Into<String>) -> Self {
+        NodeData {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
 }
 
 pub type AllNodesData = HashMap<String, NodeData>;
@@ -1,87 +1,280 @@
-use crate::models::{AllNodesData, NodeData};
-use crate::parser::get_table_data;
+use crate::accounting::accounting_report;
+use crate::config_matrix::compare_configs;
+use crate::database::{
+    node_db_path, query_table, run_query, ColumnFilter, InvalidQuery, TableQuery, UnknownTable,
+};
+use crate::dot::parse_gossip_graph;
+use crate::export::{entry_range, export_archive, export_entry, export_file_name, ExportFormat};
+use crate::gossip::{analyze_convergence, correlate_gossip};
+use crate::graph_metrics::{graph_metrics, metrics_series};
+use crate::identity::IdentityRegistry;
+use crate::log_index::LogIndexCache;
+use crate::logs::{
+    detect_encoding, filter_page, log_file_path, normalize_timestamp_bound, page_bounds,
+    read_log_text, text_file_name, LogEncoding, LogFilter,
+};
+use crate::models::{
+    AllNodesData, ConfigDifference, LogRecord, NodeConfig, NodeData, RouteEdgeCount, RouteRecord,
+    TimelineRecord,
+};
+use crate::redact::Redactor;
+use crate::tail::{LogTail, TailEvent};
+use crate::timeline::{node_color, node_log_sources, TimelineMerge};
+use crate::topology::build_topology;
 use axum::{
     extract::{Path, Query, State},
-    response::{Html, IntoResponse},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse,
+    },
     Json,
 };
 use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::collections::HashMap;
+use std::convert::Infallible;
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use tera::{Context, Tera};
+use tokio_stream::wrappers::ReceiverStream;
+
+/// How often a live tail checks its log for new lines
+const TAIL_POLL_INTERVAL: Duration = Duration::from_millis(500);
 
 #[derive(Clone)]
 pub struct AppState {
     pub nodes_data: Arc<RwLock<AllNodesData>>,
     pub input_dir: PathBuf,
     pub tera: Arc<Tera>,
+    pub log_index: Arc<LogIndexCache>,
+    /// Applied to config, log and database contents before they are served
+    pub redactor: Arc<Redactor>,
+    /// Built from `nodes_data`, and rebuilt whenever the watcher updates it
+    pub identities: Arc<RwLock<IdentityRegistry>>,
 }
 
 #[derive(Deserialize)]
 pub struct LogRangeParams {
+    /// Index of the first record to return
     pub start: Option<usize>,
+    /// Number of records to return
     pub lines: Option<usize>,
     #[serde(rename = "fromEnd")]
     pub from_end: Option<String>, // "true" or "false"
 }
 
+/// Server-side log filters shared by every log endpoint.
+/// List values are comma-separated.
+#[derive(Deserialize, Default)]
+pub struct LogFilterParams {
+    /// Minimum level (TRACE, DEBUG, INFO, WARN, ERROR)
+    pub level: Option<String>,
+    pub components: Option<String>,
+    pub exclude: Option<String>,
+    pub thread: Option<String>,
+    pub regex: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+impl LogFilterParams {
+    pub fn to_filter(&self) -> Result<LogFilter, String> {
+        fn non_empty(value: &Option<String>) -> Option<&str> {
+            value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+        }
+        fn split_list(value: &Option<String>) -> Vec<String> {
+            non_empty(value)
+                .map(|v| {
+                    v.split(',')
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default()
+        }
+
+        Ok(LogFilter {
+            min_level: non_empty(&self.level).map(str::parse).transpose()?,
+            components: split_list(&self.components),
+            exclude_components: split_list(&self.exclude),
+            thread: non_empty(&self.thread).map(str::to_string),
+            pattern: non_empty(&self.regex)
+                .map(|r| regex::Regex::new(r).map_err(|e| format!("Invalid regex: {}", e)))
+                .transpose()?,
+            since: non_empty(&self.since).map(normalize_timestamp_bound),
+            until: non_empty(&self.until).map(normalize_timestamp_bound),
+        })
+    }
+}
+
 #[derive(Serialize)]
 pub struct LogResponse {
-    pub lines: Vec<String>,
-    #[serde(rename = "totalLines")]
-    pub total_lines: usize,
+    pub records: Vec<LogRecord>,
+    /// Number of records matching the filters; `start`/`end` index into these
+    #[serde(rename = "totalRecords")]
+    pub total_records: usize,
+    /// Number of records in the file before filtering
+    #[serde(rename = "totalUnfiltered")]
+    pub total_unfiltered: usize,
     pub start: usize,
     pub end: usize,
 }
 
 // --- API Handlers ---
 
-pub async fn get_log_range(
-    State(state): State<AppState>,
-    Path((node_name, file_name)): Path<(String, String)>,
-    Query(params): Query<LogRangeParams>,
-) -> impl IntoResponse {
-    // Look up the correct path from NodeData
+/// Resolves a log file name from the URL to its location on disk.
+/// Flat-structure nodes store full paths in `log_files`; nested nodes store
+/// bare file names relative to their node directory.
+pub fn resolve_log_path(state: &AppState, node_name: &str, file_name: &str) -> PathBuf {
     let nodes = state.nodes_data.read().unwrap();
-    let log_path = if let Some(node) = nodes.get(&node_name) {
-        // Find matching log file path
-        let stored_path = node.log_files.iter().find(|path| {
+    let stored_path = nodes.get(node_name).and_then(|node| {
+        node.log_files.iter().find(|path| {
             PathBuf::from(path)
                 .file_name()
                 .and_then(|n| n.to_str())
                 .map(|n| n == file_name)
                 .unwrap_or(false)
-        });
-
-        if let Some(path_str) = stored_path {
-            let path = PathBuf::from(path_str);
-            // Check if this is a full/relative path or just a filename
-            if path.parent().is_some() && path.parent() != Some(std::path::Path::new("")) {
-                // It's a path with directory components (flat structure)
-                path
-            } else {
-                // It's just a filename (nested structure), construct full path
-                state.input_dir.join(&node_name).join(&file_name)
-            }
+        })
+    });
+
+    log_file_path(
+        &state.input_dir,
+        node_name,
+        stored_path.map(String::as_str).unwrap_or(file_name),
+    )
+}
+
+pub async fn get_log_range(
+    State(state): State<AppState>,
+    Path((node_name, file_name)): Path<(String, String)>,
+    Query(params): Query<LogRangeParams>,
+    Query(filter_params): Query<LogFilterParams>,
+) -> impl IntoResponse {
+    let filter = match filter_params.to_filter() {
+        Ok(filter) => filter,
+        Err(e) => return (axum::http::StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    let log_path = resolve_log_path(&state, &node_name, &file_name);
+
+    if !log_path.exists() {
+        return (axum::http::StatusCode::NOT_FOUND, "Log file not found").into_response();
+    }
+
+    let num_records = params.lines.unwrap_or(1000);
+    let from_end = params.from_end.as_deref() == Some("true");
+
+    // Building the index may decompress an archive or scan a large log
+    let page = tokio::task::spawn_blocking(move || {
+        let index = state
+            .log_index
+            .get(&log_path)
+            .map_err(|_| "Failed to open log file")?;
+        let total_unfiltered = index.len();
+
+        let redactor = &state.redactor;
+        let page = if filter.is_empty() {
+            // Seek straight to the requested records through the index
+            let total_records = total_unfiltered;
+            let (start, end) = page_bounds(total_records, params.start, num_records, from_end);
+            index.read_records(start, end).map(|mut records| {
+                records
+                    .iter_mut()
+                    .for_each(|record| redactor.redact_record(record));
+                (records, total_records, start, end)
+            })
         } else {
-            // Not found in log_files, fallback to constructed path
-            state.input_dir.join(&node_name).join(&file_name)
+            // Filters have to look at every record, but only the page is kept.
+            // They see records redacted, so they cannot be used to probe secrets.
+            index.records().map(|records| {
+                filter_page(
+                    records.map_while(Result::ok).map(|mut record| {
+                        redactor.redact_record(&mut record);
+                        record
+                    }),
+                    |record| filter.matches(record),
+                    params.start,
+                    num_records,
+                    from_end,
+                )
+            })
+        };
+        page.map(|page| (page, total_unfiltered))
+            .map_err(|_| "Failed to read log file")
+    })
+    .await;
+
+    let ((records, total_records, start, end), total_unfiltered) = match page {
+        Ok(Ok(page)) => page,
+        Ok(Err(message)) => {
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, message).into_response()
         }
-    } else {
-        // Node not found, fallback to constructed path
-        state.input_dir.join(&node_name).join(&file_name)
+        Err(_) => {
+            return (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                "Log task failed",
+            )
+                .into_response()
+        }
+    };
+
+    Json(LogResponse {
+        records,
+        total_records,
+        total_unfiltered,
+        start,
+        end,
+    })
+    .into_response()
+}
+
+#[derive(Deserialize)]
+pub struct LogTailParams {
+    /// Index of the first record to stream, as counted by `totalUnfiltered`;
+    /// defaults to the end of the log
+    pub start: Option<usize>,
+}
+
+/// Streams records appended to a log as Server-Sent Events: `records` events
+/// carry a JSON array of records passing the filters, and a `reset` event
+/// (`truncated` or `rotated`) announces that the log started over.
+pub async fn tail_log(
+    State(state): State<AppState>,
+    Path((node_name, file_name)): Path<(String, String)>,
+    Query(params): Query<LogTailParams>,
+    Query(filter_params): Query<LogFilterParams>,
+) -> impl IntoResponse {
+    let filter = match filter_params.to_filter() {
+        Ok(filter) => filter,
+        Err(e) => return (axum::http::StatusCode::BAD_REQUEST, e).into_response(),
     };
-    drop(nodes);
+
+    let log_path = resolve_log_path(&state, &node_name, &file_name);
 
     if !log_path.exists() {
         return (axum::http::StatusCode::NOT_FOUND, "Log file not found").into_response();
     }
+    if !matches!(detect_encoding(&log_path), Ok(LogEncoding::Plain)) {
+        return (
+            axum::http::StatusCode::BAD_REQUEST,
+            "Only plain-text logs can be tailed",
+        )
+            .into_response();
+    }
 
-    let file = match File::open(&log_path) {
-        Ok(f) => f,
-        Err(_) => {
+    // The first tail of a log builds its index, which reads all of it
+    let log_index = state.log_index.clone();
+    let tail_path = log_path.clone();
+    let tail = tokio::task::spawn_blocking(move || {
+        log_index.get(&tail_path).and_then(|index| {
+            let (offset, line) = index.position_of(params.start.unwrap_or(index.len()));
+            LogTail::new(&tail_path, offset, line as usize)
+        })
+    })
+    .await;
+    let mut tail = match tail {
+        Ok(Ok(tail)) => tail,
+        _ => {
             return (
                 axum::http::StatusCode::INTERNAL_SERVER_ERROR,
                 "Failed to open log file",
@@ -90,34 +283,151 @@ pub async fn get_log_range(
         }
     };
 
-    let reader = BufReader::new(file);
-    let all_lines: Vec<String> = reader.lines().map_while(Result::ok).collect();
-    let total_lines = all_lines.len();
+    let redactor = state.redactor.clone();
+    let (tx, rx) = tokio::sync::mpsc::channel::<Result<Event, Infallible>>(16);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(TAIL_POLL_INTERVAL);
+        // Stops once the client disconnects and the receiver is dropped
+        while !tx.is_closed() {
+            interval.tick().await;
+            // The tail moves to the blocking pool for the read and comes back
+            let polled = tokio::task::spawn_blocking(move || {
+                let events = tail.poll();
+                (tail, events)
+            })
+            .await;
+            let events = match polled {
+                Ok((polled_tail, Ok(events))) => {
+                    tail = polled_tail;
+                    events
+                }
+                Ok((_, Err(e))) => {
+                    eprintln!("Stopped tailing {:?}: {}", log_path, e);
+                    break;
+                }
+                Err(e) => {
+                    eprintln!("Stopped tailing {:?}: {}", log_path, e);
+                    break;
+                }
+            };
 
-    let num_lines = params.lines.unwrap_or(1000);
-    let from_end = params.from_end.as_deref() == Some("true");
+            for event in events {
+                let event = match event {
+                    TailEvent::Records(records) => {
+                        let records: Vec<LogRecord> = records
+                            .into_iter()
+                            .map(|mut r| {
+                                redactor.redact_record(&mut r);
+                                r
+                            })
+                            .filter(|r| filter.matches(r))
+                            .collect();
+                        if records.is_empty() {
+                            continue;
+                        }
+                        match Event::default().event("records").json_data(&records) {
+                            Ok(event) => event,
+                            Err(e) => {
+                                eprintln!("Failed to encode tailed records: {}", e);
+                                continue;
+                            }
+                        }
+                    }
+                    TailEvent::Reset(reset) => Event::default().event("reset").data(reset.as_str()),
+                };
+                if tx.send(Ok(event)).await.is_err() {
+                    return;
+                }
+            }
+        }
+    });
 
-    let (start, end, lines) = if from_end {
-        let start = total_lines.saturating_sub(num_lines);
-        let end = total_lines;
-        let lines = all_lines[start..end].to_vec();
-        (start, end, lines)
-    } else {
-        let start = params.start.unwrap_or(0);
-        let end = (start + num_lines).min(total_lines);
-        let lines = if start < total_lines {
-            all_lines[start..end].to_vec()
-        } else {
-            Vec::new()
-        };
-        (start, end, lines)
+    Sse::new(ReceiverStream::new(rx))
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// Node selection for the merged timeline (comma-separated; all nodes if absent)
+#[derive(Deserialize, Default)]
+pub struct TimelineParams {
+    pub nodes: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TimelineNode {
+    pub name: String,
+    pub color: String,
+}
+
+#[derive(Serialize)]
+pub struct TimelineResponse {
+    pub records: Vec<TimelineRecord>,
+    #[serde(rename = "totalRecords")]
+    pub total_records: usize,
+    pub start: usize,
+    pub end: usize,
+    pub nodes: Vec<TimelineNode>,
+}
+
+pub async fn get_timeline(
+    State(state): State<AppState>,
+    Query(params): Query<LogRangeParams>,
+    Query(filter_params): Query<LogFilterParams>,
+    Query(timeline_params): Query<TimelineParams>,
+) -> impl IntoResponse {
+    let filter = match filter_params.to_filter() {
+        Ok(filter) => filter,
+        Err(e) => return (axum::http::StatusCode::BAD_REQUEST, e).into_response(),
     };
+    let only: Option<Vec<String>> = timeline_params.nodes.as_deref().map(|n| {
+        n.split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    });
 
-    Json(LogResponse {
-        lines,
-        total_lines,
+    // Only the log paths are taken out, so the merge runs without the lock
+    let sources = {
+        let nodes = state.nodes_data.read().unwrap();
+        node_log_sources(&state.input_dir, &nodes, only.as_deref())
+    };
+    let nodes = sources
+        .iter()
+        .map(|s| TimelineNode {
+            name: s.node.clone(),
+            color: s.color.clone(),
+        })
+        .collect();
+
+    // Merging reads every log and may decompress archives and build indexes
+    let page = tokio::task::spawn_blocking(move || {
+        let redactor = &state.redactor;
+        filter_page(
+            TimelineMerge::new(sources, &state.log_index).map(|mut entry| {
+                redactor.redact_record(&mut entry.record);
+                entry
+            }),
+            |entry| filter.matches(&entry.record),
+            params.start,
+            params.lines.unwrap_or(1000),
+            params.from_end.as_deref() == Some("true"),
+        )
+    })
+    .await;
+    let Ok((records, total_records, start, end)) = page else {
+        return (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            "Timeline task failed",
+        )
+            .into_response();
+    };
+
+    Json(TimelineResponse {
+        records,
+        total_records,
         start,
         end,
+        nodes,
     })
     .into_response()
 }
@@ -126,59 +436,35 @@ pub async fn download_log(
     State(state): State<AppState>,
     Path((node_name, file_name)): Path<(String, String)>,
 ) -> impl IntoResponse {
-    // Look up the correct path from NodeData
-    let nodes = state.nodes_data.read().unwrap();
-    let log_path = if let Some(node) = nodes.get(&node_name) {
-        // Find matching log file path
-        let stored_path = node.log_files.iter().find(|path| {
-            PathBuf::from(path)
-                .file_name()
-                .and_then(|n| n.to_str())
-                .map(|n| n == file_name)
-                .unwrap_or(false)
-        });
-
-        if let Some(path_str) = stored_path {
-            let path = PathBuf::from(path_str);
-            // Check if this is a full/relative path or just a filename
-            if path.parent().is_some() && path.parent() != Some(std::path::Path::new("")) {
-                // It's a path with directory components (flat structure)
-                path
-            } else {
-                // It's just a filename (nested structure), construct full path
-                state.input_dir.join(&node_name).join(&file_name)
-            }
-        } else {
-            // Not found in log_files, fallback to constructed path
-            state.input_dir.join(&node_name).join(&file_name)
-        }
-    } else {
-        // Node not found, fallback to constructed path
-        state.input_dir.join(&node_name).join(&file_name)
-    };
-    drop(nodes);
+    let log_path = resolve_log_path(&state, &node_name, &file_name);
 
     if !log_path.exists() {
         return (axum::http::StatusCode::NOT_FOUND, "Log file not found").into_response();
     }
 
-    // Extract just the filename for the attachment header
-    let attachment_name = log_path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or(&file_name);
+    // Extract just the filename for the attachment header; archives are
+    // served decompressed, so drop their archive extension
+    let attachment_name = text_file_name(
+        log_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(&file_name),
+    );
 
     // Serve file as attachment
-    match std::fs::read(&log_path) {
-        Ok(bytes) => (
+    match read_log_text(&log_path) {
+        Ok(text) => (
             [
-                (axum::http::header::CONTENT_TYPE, "text/plain"),
+                (
+                    axum::http::header::CONTENT_TYPE,
+                    "text/plain; charset=utf-8",
+                ),
                 (
                     axum::http::header::CONTENT_DISPOSITION,
                     &format!("attachment; filename=\"{}\"", attachment_name),
                 ),
             ],
-            bytes,
+            state.redactor.redact_text(&text).into_owned(),
         )
             .into_response(),
         Err(_) => (
@@ -203,30 +489,138 @@ pub async fn get_db_tables(
     }
 }
 
+/// Rows per page when a table request gives no `limit`.
+const DEFAULT_TABLE_PAGE: usize = 100;
+/// Largest `limit` a table request may ask for.
+const MAX_TABLE_PAGE: usize = 10_000;
+
+/// Paging, sorting and search of table rows. Column filters are separate
+/// `eq.<column>`, `min.<column>`, `max.<column>` and `like.<column>`
+/// parameters.
+#[derive(Deserialize)]
+pub struct TablePageParams {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+    pub order_by: Option<String>,
+    pub desc: Option<bool>,
+    pub search: Option<String>,
+}
+
+impl TablePageParams {
+    fn to_query(&self, params: &HashMap<String, String>) -> TableQuery {
+        let mut filters: Vec<ColumnFilter> = params
+            .iter()
+            .filter_map(|(key, value)| ColumnFilter::from_param(key, value))
+            .collect();
+        // Parameter order is lost in the map; keep queries reproducible
+        filters.sort_by(|a, b| a.column.cmp(&b.column));
+        TableQuery {
+            offset: self.offset.unwrap_or(0),
+            limit: Some(self.limit.unwrap_or(DEFAULT_TABLE_PAGE).min(MAX_TABLE_PAGE)),
+            order_by: self.order_by.clone().filter(|c| !c.is_empty()),
+            desc: self.desc.unwrap_or(false),
+            filters,
+            search: self.search.clone(),
+        }
+    }
+}
+
 pub async fn get_db_table_data(
     State(state): State<AppState>,
     Path((node_name, table_name)): Path<(String, String)>,
+    Query(page_params): Query<TablePageParams>,
+    Query(params): Query<HashMap<String, String>>,
 ) -> impl IntoResponse {
-    // Try nested structure first
-    let mut db_path = state.input_dir.join(&node_name).join("node-data.db");
+    let Some(db_path) = node_db_path(&state.input_dir, &node_name) else {
+        return (axum::http::StatusCode::NOT_FOUND, "Database not found").into_response();
+    };
+
+    let query = page_params.to_query(&params);
+    let result = tokio::task::spawn_blocking(move || {
+        query_table(&db_path, &table_name, &query, &state.redactor).map(|mut page| {
+            page.redact(&state.redactor);
+            page
+        })
+    })
+    .await;
 
-    // If nested structure database doesn't exist, try flat structure
-    if !db_path.exists() {
-        // Flat structure: {node_name}-node-data.db in input_dir
-        db_path = state.input_dir.join(format!("{}-node-data.db", node_name));
+    match result {
+        Ok(Ok(page)) => Json(page).into_response(),
+        Ok(Err(e)) if e.is::<UnknownTable>() => {
+            (axum::http::StatusCode::NOT_FOUND, "Table not found").into_response()
+        }
+        Ok(Err(e)) if e.is::<InvalidQuery>() => {
+            (axum::http::StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
+        Ok(Err(e)) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to query database: {}", e),
+        )
+            .into_response(),
+        Err(_) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            "Query task failed",
+        )
+            .into_response(),
     }
+}
+
+/// Rows an SQL console query returns when it gives no `limit`.
+const DEFAULT_QUERY_ROWS: usize = 1000;
+/// How long an SQL console query may run.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize)]
+pub struct DbQueryRequest {
+    /// A single `SELECT` statement
+    pub sql: String,
+    /// At most `MAX_TABLE_PAGE`
+    pub limit: Option<usize>,
+}
 
-    if !db_path.exists() {
+pub async fn post_db_query(
+    State(state): State<AppState>,
+    Path(node_name): Path<String>,
+    Json(request): Json<DbQueryRequest>,
+) -> impl IntoResponse {
+    let Some(db_path) = node_db_path(&state.input_dir, &node_name) else {
         return (axum::http::StatusCode::NOT_FOUND, "Database not found").into_response();
-    }
+    };
 
-    match get_table_data(&db_path, &table_name) {
-        Ok(data) => Json(data).into_response(),
-        Err(e) => (
+    let limit = request
+        .limit
+        .unwrap_or(DEFAULT_QUERY_ROWS)
+        .min(MAX_TABLE_PAGE);
+    let result = tokio::task::spawn_blocking(move || {
+        run_query(
+            &db_path,
+            &request.sql,
+            limit,
+            QUERY_TIMEOUT,
+            &state.redactor,
+        )
+        .map(|mut result| {
+            state.redactor.redact_table(&mut result.table);
+            result
+        })
+    })
+    .await;
+
+    match result {
+        Ok(Ok(result)) => Json(result).into_response(),
+        Ok(Err(e)) if e.is::<InvalidQuery>() => {
+            (axum::http::StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
+        Ok(Err(e)) => (
             axum::http::StatusCode::INTERNAL_SERVER_ERROR,
             format!("Failed to query database: {}", e),
         )
             .into_response(),
+        Err(_) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            "Query task failed",
+        )
+            .into_response(),
     }
 }
 
@@ -242,6 +636,365 @@ pub async fn get_gossip(
     }
 }
 
+/// Gossip-related log lines of a node that carry no graph: Debuts, Passes,
+/// Introductions, rejections and `GossipFailure`s, with their reasons.
+pub async fn get_gossip_events(
+    State(state): State<AppState>,
+    Path(node_name): Path<String>,
+) -> impl IntoResponse {
+    let nodes = state.nodes_data.read().unwrap();
+    if let Some(node) = nodes.get(&node_name) {
+        Json(&node.gossip_events).into_response()
+    } else {
+        (axum::http::StatusCode::NOT_FOUND, "Node not found").into_response()
+    }
+}
+
+/// A gossip entry of a node, parsed into nodes and edges. The index counts
+/// all gossip entries of the node, as returned by `get_gossip`.
+pub async fn get_gossip_graph(
+    State(state): State<AppState>,
+    Path((node_name, index)): Path<(String, usize)>,
+) -> impl IntoResponse {
+    let nodes = state.nodes_data.read().unwrap();
+    let Some(node) = nodes.get(&node_name) else {
+        return (axum::http::StatusCode::NOT_FOUND, "Node not found").into_response();
+    };
+    let Some(entry) = node.gossip.get(index) else {
+        return (axum::http::StatusCode::NOT_FOUND, "Gossip entry not found").into_response();
+    };
+
+    match parse_gossip_graph(&entry.dot) {
+        Ok(graph) => Json(graph).into_response(),
+        Err(e) => (
+            axum::http::StatusCode::UNPROCESSABLE_ENTITY,
+            format!("Failed to parse gossip graph: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+/// Counts, connectivity and routability of one gossip entry of a node,
+/// indexed as by `get_gossip`.
+pub async fn get_gossip_metrics(
+    State(state): State<AppState>,
+    Path((node_name, index)): Path<(String, usize)>,
+) -> impl IntoResponse {
+    let nodes = state.nodes_data.read().unwrap();
+    let Some(node) = nodes.get(&node_name) else {
+        return (axum::http::StatusCode::NOT_FOUND, "Node not found").into_response();
+    };
+    let Some(entry) = node.gossip.get(index) else {
+        return (axum::http::StatusCode::NOT_FOUND, "Gossip entry not found").into_response();
+    };
+
+    match parse_gossip_graph(&entry.dot) {
+        Ok(graph) => {
+            let registry = state.identities.read().unwrap();
+            Json(graph_metrics(&graph, &registry)).into_response()
+        }
+        Err(e) => (
+            axum::http::StatusCode::UNPROCESSABLE_ENTITY,
+            format!("Failed to parse gossip graph: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+/// The metrics of every gossip entry of a node that parses, in log order.
+pub async fn get_gossip_metrics_series(
+    State(state): State<AppState>,
+    Path(node_name): Path<String>,
+) -> impl IntoResponse {
+    let nodes = state.nodes_data.read().unwrap();
+    if let Some(node) = nodes.get(&node_name) {
+        let registry = state.identities.read().unwrap();
+        Json(metrics_series(node, &registry)).into_response()
+    } else {
+        (axum::http::StatusCode::NOT_FOUND, "Node not found").into_response()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct GossipExportParams {
+    pub format: ExportFormat,
+}
+
+/// One gossip entry of a node as a DOT, GraphML or SVG attachment.
+pub async fn get_gossip_export(
+    State(state): State<AppState>,
+    Path((node_name, index)): Path<(String, usize)>,
+    Query(params): Query<GossipExportParams>,
+) -> impl IntoResponse {
+    let nodes = state.nodes_data.read().unwrap();
+    let Some(node) = nodes.get(&node_name) else {
+        return (axum::http::StatusCode::NOT_FOUND, "Node not found").into_response();
+    };
+    let Some(entry) = node.gossip.get(index) else {
+        return (axum::http::StatusCode::NOT_FOUND, "Gossip entry not found").into_response();
+    };
+
+    let registry = state.identities.read().unwrap();
+    match export_entry(&node_name, index, entry, params.format, &registry) {
+        Ok(content) => (
+            [
+                (
+                    axum::http::header::CONTENT_TYPE,
+                    params.format.content_type(),
+                ),
+                (
+                    axum::http::header::CONTENT_DISPOSITION,
+                    &format!(
+                        "attachment; filename=\"{}\"",
+                        export_file_name(&node_name, index, entry, params.format)
+                    ),
+                ),
+            ],
+            content,
+        )
+            .into_response(),
+        Err(e) => (
+            axum::http::StatusCode::UNPROCESSABLE_ENTITY,
+            format!("Failed to parse gossip graph: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+/// Entries to export; `from` defaults to the first and `to` to the last.
+#[derive(Deserialize)]
+pub struct GossipExportRangeParams {
+    pub format: ExportFormat,
+    pub from: Option<usize>,
+    pub to: Option<usize>,
+}
+
+/// A range of a node's gossip entries as a ZIP archive of one file each.
+pub async fn get_gossip_export_range(
+    State(state): State<AppState>,
+    Path(node_name): Path<String>,
+    Query(params): Query<GossipExportRangeParams>,
+) -> impl IntoResponse {
+    let nodes = state.nodes_data.read().unwrap();
+    let Some(node) = nodes.get(&node_name) else {
+        return (axum::http::StatusCode::NOT_FOUND, "Node not found").into_response();
+    };
+    let range = match entry_range(node, params.from, params.to) {
+        Ok(range) => range,
+        Err(e) => return (axum::http::StatusCode::NOT_FOUND, e.to_string()).into_response(),
+    };
+
+    let registry = state.identities.read().unwrap();
+    let file_name = format!(
+        "{}_gossip_{}-{}_{}.zip",
+        node_name,
+        range.start(),
+        range.end(),
+        params.format.extension()
+    );
+    match export_archive(node, range, params.format, &registry) {
+        Ok(archive) => (
+            [
+                (axum::http::header::CONTENT_TYPE, "application/zip"),
+                (
+                    axum::http::header::CONTENT_DISPOSITION,
+                    &format!("attachment; filename=\"{}\"", file_name),
+                ),
+            ],
+            archive,
+        )
+            .into_response(),
+        Err(e) => (
+            axum::http::StatusCode::UNPROCESSABLE_ENTITY,
+            format!("Failed to export gossip: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+/// Two entries of a node's gossip to compare; `to` defaults to the entry
+/// after `from`.
+#[derive(Deserialize)]
+pub struct GossipDiffParams {
+    pub from: usize,
+    pub to: Option<usize>,
+}
+
+/// Nodes and edges added, removed or changed between two gossip entries of
+/// a node, indexed as by `get_gossip`.
+pub async fn get_gossip_diff(
+    State(state): State<AppState>,
+    Path(node_name): Path<String>,
+    Query(params): Query<GossipDiffParams>,
+) -> impl IntoResponse {
+    let nodes = state.nodes_data.read().unwrap();
+    let Some(node) = nodes.get(&node_name) else {
+        return (axum::http::StatusCode::NOT_FOUND, "Node not found").into_response();
+    };
+    let Some(to) = params.to.or(params.from.checked_add(1)) else {
+        return (
+            axum::http::StatusCode::BAD_REQUEST,
+            "No gossip entry follows 'from'",
+        )
+            .into_response();
+    };
+    let (Some(older), Some(newer)) = (node.gossip.get(params.from), node.gossip.get(to)) else {
+        return (axum::http::StatusCode::NOT_FOUND, "Gossip entry not found").into_response();
+    };
+
+    match (
+        parse_gossip_graph(&older.dot),
+        parse_gossip_graph(&newer.dot),
+    ) {
+        (Ok(older), Ok(newer)) => Json(older.diff(&newer)).into_response(),
+        (Err(e), _) | (_, Err(e)) => (
+            axum::http::StatusCode::UNPROCESSABLE_ENTITY,
+            format!("Failed to parse gossip graph: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+/// Sent gossip matched to its receipt on the target node, and how each node
+/// record version spread through the network.
+pub async fn get_gossip_propagation(State(state): State<AppState>) -> impl IntoResponse {
+    let nodes = state.nodes_data.read().unwrap();
+    Json(correlate_gossip(&nodes, &state.identities.read().unwrap()))
+}
+
+/// When and whether the nodes' `Current database` dumps agreed on the
+/// network topology.
+pub async fn get_gossip_convergence(State(state): State<AppState>) -> impl IntoResponse {
+    let nodes = state.nodes_data.read().unwrap();
+    Json(analyze_convergence(
+        &nodes,
+        &state.identities.read().unwrap(),
+    ))
+}
+
+/// Which local node each known public key belongs to, and the gossip keys
+/// that belong to none.
+pub async fn get_identities(State(state): State<AppState>) -> impl IntoResponse {
+    let registry = state.identities.read().unwrap();
+    Json(&*registry).into_response()
+}
+
+/// The latest `Current database` of every node and all logged routes merged
+/// into one graph.
+pub async fn get_network_topology(State(state): State<AppState>) -> impl IntoResponse {
+    let nodes = state.nodes_data.read().unwrap();
+    Json(build_topology(&nodes, &state.identities.read().unwrap()))
+}
+
+#[derive(Serialize)]
+pub struct RoutesResponse<'a> {
+    pub routes: &'a [RouteRecord],
+    /// How often each hop was part of a route, busiest first
+    pub edges: Vec<RouteEdgeCount>,
+}
+
+/// Every route a node logged, in log order, with per-edge usage counts.
+pub async fn get_routes(
+    State(state): State<AppState>,
+    Path(node_name): Path<String>,
+) -> impl IntoResponse {
+    let nodes = state.nodes_data.read().unwrap();
+    if let Some(node) = nodes.get(&node_name) {
+        Json(RoutesResponse {
+            routes: &node.routes,
+            edges: RouteEdgeCount::aggregate(&node.routes),
+        })
+        .into_response()
+    } else {
+        (axum::http::StatusCode::NOT_FOUND, "Node not found").into_response()
+    }
+}
+
+#[derive(Serialize)]
+pub struct ConfigResponse {
+    pub config: Option<NodeConfig>,
+    #[serde(rename = "configDel")]
+    pub config_del: Option<NodeConfig>,
+    /// Settings that differ between `config.toml` (left) and `config.del` (right)
+    pub diff: Vec<ConfigDifference>,
+}
+
+pub async fn get_config(
+    State(state): State<AppState>,
+    Path(node_name): Path<String>,
+) -> impl IntoResponse {
+    let nodes = state.nodes_data.read().unwrap();
+    if let Some(node) = nodes.get(&node_name) {
+        let redactor = &state.redactor;
+        // Compared unmasked, as two different secrets look the same masked
+        let empty = NodeConfig::default();
+        let diff = match (&node.config, &node.config_del) {
+            (None, None) => Vec::new(),
+            (config, config_del) => config
+                .as_ref()
+                .unwrap_or(&empty)
+                .diff(config_del.as_ref().unwrap_or(&empty)),
+        }
+        .into_iter()
+        .map(|difference| ConfigDifference {
+            left: difference
+                .left
+                .map(|value| redactor.redact_setting(&difference.key, &value)),
+            right: difference
+                .right
+                .map(|value| redactor.redact_setting(&difference.key, &value)),
+            key: difference.key,
+        })
+        .collect();
+
+        let config = node.config.as_ref().map(|c| redactor.redact_config(c));
+        let config_del = node.config_del.as_ref().map(|c| redactor.redact_config(c));
+        Json(ConfigResponse {
+            config,
+            config_del,
+            diff,
+        })
+        .into_response()
+    } else {
+        (axum::http::StatusCode::NOT_FOUND, "Node not found").into_response()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AccountingParams {
+    /// Unix seconds to measure debt ages at, now by default
+    pub at: Option<i64>,
+}
+
+/// Payables of every node matched to the receivables of the node they are
+/// owed to, with debts past the debtor's payment thresholds marked overdue.
+pub async fn get_accounting(
+    State(state): State<AppState>,
+    Query(params): Query<AccountingParams>,
+) -> impl IntoResponse {
+    let at = params.at.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default()
+    });
+    // Every node database is opened, so keep it off the async workers
+    let report = tokio::task::spawn_blocking(move || {
+        let nodes = state.nodes_data.read().unwrap();
+        accounting_report(&nodes, &state.input_dir, at)
+    })
+    .await;
+
+    match report {
+        Ok(report) => Json(report).into_response(),
+        Err(_) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            "Accounting task failed",
+        )
+            .into_response(),
+    }
+}
+
 // --- View Handlers ---
 
 pub async fn dashboard(State(state): State<AppState>) -> impl IntoResponse {
@@ -256,6 +1009,9 @@ pub async fn dashboard(State(state): State<AppState>) -> impl IntoResponse {
     context.insert("nodes", &nodes_vec);
     context.insert("allNodes", &all_nodes);
     context.insert("inputDir", &state.input_dir.to_string_lossy());
+    let mut config_matrix = compare_configs(&nodes);
+    state.redactor.redact_matrix(&mut config_matrix);
+    context.insert("configMatrix", &config_matrix);
 
     // We also need fileTree for the dashboard...
     // Implementing a simple file tree structure
@@ -312,6 +1068,70 @@ pub async fn node_view(
     }
 }
 
+pub async fn timeline_view(State(state): State<AppState>) -> impl IntoResponse {
+    let nodes = state.nodes_data.read().unwrap();
+    let mut context = Context::new();
+
+    let mut all_nodes: Vec<&String> = nodes.keys().collect();
+    all_nodes.sort(); // Sort alphabetically
+    let node_colors: Vec<TimelineNode> = all_nodes
+        .iter()
+        .enumerate()
+        .map(|(position, name)| TimelineNode {
+            name: name.to_string(),
+            color: node_color(position).to_string(),
+        })
+        .collect();
+
+    context.insert("allNodes", &all_nodes);
+    context.insert("nodeColors", &node_colors);
+
+    match state.tera.render("timeline.html", &context) {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Template error: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn network_view(State(state): State<AppState>) -> impl IntoResponse {
+    let nodes = state.nodes_data.read().unwrap();
+    let mut context = Context::new();
+
+    let mut all_nodes: Vec<&String> = nodes.keys().collect();
+    all_nodes.sort(); // Sort alphabetically
+    context.insert("allNodes", &all_nodes);
+
+    match state.tera.render("network.html", &context) {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Template error: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn accounting_view(State(state): State<AppState>) -> impl IntoResponse {
+    let nodes = state.nodes_data.read().unwrap();
+    let mut context = Context::new();
+
+    let mut all_nodes: Vec<&String> = nodes.keys().collect();
+    all_nodes.sort();
+    context.insert("allNodes", &all_nodes);
+
+    match state.tera.render("accounting.html", &context) {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Template error: {}", e),
+        )
+            .into_response(),
+    }
+}
+
 // Helper for file tree
 #[derive(Serialize)]
 pub struct FileTreeItem {
@@ -0,0 +1,204 @@
+use crate::models::LogRecord;
+use crate::parser::{is_log_head_line, LogRecordReader};
+use anyhow::Result;
+use std::fs::{self, File, Metadata};
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Why a tail started over from the beginning of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TailReset {
+    /// The file shrank below the position already read
+    Truncated,
+    /// The file was replaced, e.g. `_rCURRENT` rotated into `_r000NN.log.zip`
+    Rotated,
+}
+
+impl TailReset {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TailReset::Truncated => "truncated",
+            TailReset::Rotated => "rotated",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TailEvent {
+    Records(Vec<LogRecord>),
+    Reset(TailReset),
+}
+
+/// Most bytes a poll reads, so a large backlog is served over several polls
+/// rather than read into memory at once.
+pub const MAX_POLL_BYTES: u64 = 1 << 20;
+
+/// Device and inode of a file, so a rotated-away log is told apart from the
+/// new one created in its place. Not available on every platform.
+pub(crate) type FileId = Option<(u64, u64)>;
+
+#[cfg(unix)]
+pub(crate) fn file_id(metadata: &Metadata) -> FileId {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn file_id(_metadata: &Metadata) -> FileId {
+    None
+}
+
+/// Follows a growing plain-text log, turning appended lines into records.
+///
+/// A record is held back until the next head line arrives, since its
+/// continuation lines may still be on the way; it is released anyway once a
+/// poll finds nothing new. When the log is rotated, whatever was appended to
+/// the old file is read out through the still-open handle before the new
+/// file is followed from its start. Each poll reads at most
+/// `MAX_POLL_BYTES`.
+pub struct LogTail {
+    path: PathBuf,
+    file: File,
+    id: FileId,
+    position: u64,
+    next_line: usize,
+    /// Bytes of a line whose line break has not been written yet
+    partial: Vec<u8>,
+    /// Complete lines of the record being assembled
+    pending: Vec<String>,
+    pending_line: usize,
+}
+
+impl LogTail {
+    /// Starts following `path` at byte `position`, which must be the start of
+    /// line `line`. Use the end of a log index to skip what was already served.
+    pub fn new(path: &Path, position: u64, line: usize) -> Result<Self> {
+        let file = File::open(path)?;
+        let id = file_id(&file.metadata()?);
+        Ok(LogTail {
+            path: path.to_path_buf(),
+            file,
+            id,
+            position,
+            next_line: line,
+            partial: Vec::new(),
+            pending: Vec::new(),
+            pending_line: line,
+        })
+    }
+
+    /// Reads whatever was appended since the last poll.
+    pub fn poll(&mut self) -> Result<Vec<TailEvent>> {
+        let mut events = Vec::new();
+        let mut records = Vec::new();
+        let read = self.read_new_lines(&mut records)?;
+
+        // The rest of the old file is read out before a rotation is acted on
+        let reset = if read < MAX_POLL_BYTES {
+            self.detect_reset()?
+        } else {
+            None
+        };
+        match reset {
+            Some((reset, id)) => {
+                // The old file is finished: release everything it held
+                self.flush_partial(&mut records);
+                self.flush_pending(&mut records);
+                if !records.is_empty() {
+                    events.push(TailEvent::Records(std::mem::take(&mut records)));
+                }
+                events.push(TailEvent::Reset(reset));
+
+                self.file = File::open(&self.path)?;
+                self.id = id;
+                self.position = 0;
+                self.next_line = 0;
+                self.read_new_lines(&mut records)?;
+            }
+            None if read == 0 => self.flush_pending(&mut records),
+            None => {}
+        }
+
+        if !records.is_empty() {
+            events.push(TailEvent::Records(records));
+        }
+        Ok(events)
+    }
+
+    /// Reads up to `MAX_POLL_BYTES` past the position, returning how many
+    /// bytes were read.
+    fn read_new_lines(&mut self, records: &mut Vec<LogRecord>) -> Result<u64> {
+        let file = &mut self.file;
+        file.seek(SeekFrom::Start(self.position))?;
+        let mut bytes = Vec::new();
+        file.take(MAX_POLL_BYTES).read_to_end(&mut bytes)?;
+        let read = bytes.len() as u64;
+        if read == 0 {
+            return Ok(0);
+        }
+        self.position += read;
+
+        self.partial.extend_from_slice(&bytes);
+        let Some(last_break) = self.partial.iter().rposition(|&b| b == b'\n') else {
+            return Ok(read);
+        };
+        let complete: Vec<u8> = self.partial.drain(..=last_break).collect();
+        let text = String::from_utf8_lossy(&complete);
+        for line in text.split_inclusive('\n') {
+            self.push_line(line.trim_end_matches(['\n', '\r']).to_string(), records);
+        }
+        Ok(read)
+    }
+
+    fn push_line(&mut self, line: String, records: &mut Vec<LogRecord>) {
+        if is_log_head_line(&line) {
+            self.flush_pending(records);
+        }
+        if self.pending.is_empty() {
+            self.pending_line = self.next_line;
+        }
+        self.pending.push(line);
+        self.next_line += 1;
+    }
+
+    /// A line left without a line break when the file is abandoned is complete.
+    fn flush_partial(&mut self, records: &mut Vec<LogRecord>) {
+        if !self.partial.is_empty() {
+            let line = String::from_utf8_lossy(&std::mem::take(&mut self.partial))
+                .trim_end_matches('\r')
+                .to_string();
+            self.push_line(line, records);
+        }
+    }
+
+    fn flush_pending(&mut self, records: &mut Vec<LogRecord>) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let text = std::mem::take(&mut self.pending).join("\n");
+        records.extend(
+            LogRecordReader::starting_at(text.as_bytes(), self.pending_line).map_while(Result::ok),
+        );
+    }
+
+    /// Detects truncation or replacement of the file behind `path`, returning
+    /// the identity of the file to follow from now on.
+    fn detect_reset(&self) -> Result<Option<(TailReset, FileId)>> {
+        let metadata = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            // Between the rename and the creation of the new file
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let id = file_id(&metadata);
+        let reset = if id.is_some() && id != self.id {
+            TailReset::Rotated
+        } else if metadata.len() < self.position {
+            TailReset::Truncated
+        } else {
+            return Ok(None);
+        };
+        Ok(Some((reset, id)))
+    }
+}
@@ -0,0 +1,148 @@
+use crate::log_index::LogIndexCache;
+use crate::logs::log_file_path;
+use crate::models::{AllNodesData, LogRecord, TimelineRecord};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::path::{Path, PathBuf};
+
+/// Colours assigned to nodes in alphabetical order, repeating if needed.
+const NODE_COLORS: [&str; 10] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+    "#bcbd22", "#17becf",
+];
+
+pub fn node_color(position: usize) -> &'static str {
+    NODE_COLORS[position % NODE_COLORS.len()]
+}
+
+/// Log files of one node, oldest first.
+#[derive(Debug, Clone)]
+pub struct NodeLogs {
+    pub node: String,
+    pub color: String,
+    pub files: Vec<PathBuf>,
+}
+
+/// Collects the logs of every node (or only of `only`, if given). Colours are
+/// assigned over all nodes so a node keeps its colour when others are hidden.
+pub fn node_log_sources(
+    input_dir: &Path,
+    nodes: &AllNodesData,
+    only: Option<&[String]>,
+) -> Vec<NodeLogs> {
+    let mut names: Vec<&String> = nodes.keys().collect();
+    names.sort();
+
+    names
+        .into_iter()
+        .enumerate()
+        .filter(|(_, name)| only.is_none_or(|only| only.contains(name)))
+        .map(|(position, name)| {
+            // Rotated archives (`_r00000`, `_r00001`, ...) sort before `_rCURRENT`
+            let mut files: Vec<PathBuf> = nodes[name]
+                .log_files
+                .iter()
+                .map(|stored| log_file_path(input_dir, name, stored))
+                .collect();
+            files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+
+            NodeLogs {
+                node: name.clone(),
+                color: node_color(position).to_string(),
+                files,
+            }
+        })
+        .collect()
+}
+
+/// Records of one node, read file after file.
+struct NodeStream<'a> {
+    logs: NodeLogs,
+    pending_files: VecDeque<PathBuf>,
+    current: Option<(String, Box<dyn Iterator<Item = LogRecord> + 'a>)>,
+    cache: &'a LogIndexCache,
+}
+
+impl NodeStream<'_> {
+    fn next_record(&mut self) -> Option<TimelineRecord> {
+        loop {
+            if let Some((file, records)) = self.current.as_mut() {
+                // Records without a timestamp (startup banner) cannot be placed
+                if let Some(record) = records.by_ref().find(|r| r.timestamp.is_some()) {
+                    return Some(TimelineRecord {
+                        node: self.logs.node.clone(),
+                        color: self.logs.color.clone(),
+                        file: file.clone(),
+                        record,
+                    });
+                }
+            }
+
+            let path = self.pending_files.pop_front()?;
+            let file = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            match self.cache.get(&path).and_then(|index| index.records()) {
+                Ok(records) => {
+                    self.current = Some((file, Box::new(records.map_while(Result::ok))));
+                }
+                Err(e) => {
+                    eprintln!("Skipping {:?} on the timeline: {}", path, e);
+                    self.current = None;
+                }
+            }
+        }
+    }
+}
+
+/// K-way merge of several nodes' logs by timestamp. Each node's logs are
+/// assumed to be in chronological order already; ties are broken by node
+/// order so the merge is deterministic.
+pub struct TimelineMerge<'a> {
+    streams: Vec<NodeStream<'a>>,
+    heads: Vec<Option<TimelineRecord>>,
+    heap: BinaryHeap<Reverse<(String, usize)>>,
+}
+
+impl<'a> TimelineMerge<'a> {
+    pub fn new(sources: Vec<NodeLogs>, cache: &'a LogIndexCache) -> Self {
+        let mut merge = TimelineMerge {
+            streams: Vec::new(),
+            heads: Vec::new(),
+            heap: BinaryHeap::new(),
+        };
+
+        for logs in sources {
+            let position = merge.streams.len();
+            merge.streams.push(NodeStream {
+                pending_files: logs.files.iter().cloned().collect(),
+                logs,
+                current: None,
+                cache,
+            });
+            merge.heads.push(None);
+            merge.advance(position);
+        }
+        merge
+    }
+
+    fn advance(&mut self, position: usize) {
+        let next = self.streams[position].next_record();
+        if let Some(timestamp) = next.as_ref().and_then(|r| r.record.timestamp.clone()) {
+            self.heap.push(Reverse((timestamp, position)));
+        }
+        self.heads[position] = next;
+    }
+}
+
+impl Iterator for TimelineMerge<'_> {
+    type Item = TimelineRecord;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((_, position)) = self.heap.pop()?;
+        let record = self.heads[position].take();
+        self.advance(position);
+        record
+    }
+}
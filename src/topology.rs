@@ -0,0 +1,182 @@
+use crate::gossip::parse_all_gossip;
+use crate::identity::{normalize_key, IdentityRegistry, KEY_PREFIX_LEN};
+use crate::models::{AllNodesData, GossipNode, RouteEdgeCount};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// One node of the merged network graph.
+#[derive(Debug, Serialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TopologyNode {
+    /// Public key in the standard base64 alphabet without padding
+    pub public_key: String,
+    /// The local node the key belongs to, if any
+    pub node: Option<String>,
+    /// `neighborhood-mode` from the local node's config
+    pub neighborhood_mode: Option<String>,
+    pub version: Option<u32>,
+    pub country_code: Option<String>,
+    pub ip: Option<String>,
+    pub accepts_connections: Option<bool>,
+    pub routes_data: Option<bool>,
+    /// Local nodes whose latest `Current database` lists the key
+    pub seen_by: Vec<String>,
+}
+
+/// A directed link between two nodes, known from gossip, routes or both.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TopologyEdge {
+    pub from: String,
+    pub to: String,
+    /// Local nodes whose latest `Current database` has the link
+    pub seen_by: Vec<String>,
+    /// How many logged routes crossed the link
+    pub route_count: usize,
+}
+
+/// The `Current database` dump a node contributed.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TopologySource {
+    pub node: String,
+    pub timestamp: String,
+}
+
+/// Every node's latest view of the network and its route history merged
+/// into one graph.
+#[derive(Debug, Serialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkTopology {
+    /// Sorted by public key
+    pub nodes: Vec<TopologyNode>,
+    /// Sorted by `from`, then `to`
+    pub edges: Vec<TopologyEdge>,
+    pub sources: Vec<TopologySource>,
+}
+
+impl TopologyNode {
+    /// Takes the attributes a gossip dump advertises for the node, keeping
+    /// those of the highest version seen.
+    fn merge(&mut self, gossip: &GossipNode) {
+        if self.version.is_some() && gossip.version < self.version {
+            return;
+        }
+        self.version = gossip.version.or(self.version);
+        self.country_code = gossip.country_code.clone().or(self.country_code.take());
+        self.ip = gossip.ip.clone().or(self.ip.take());
+        self.accepts_connections = gossip.accepts_connections.or(self.accepts_connections);
+        self.routes_data = gossip.routes_data.or(self.routes_data);
+    }
+}
+
+/// Merges the latest `Current database` dump of every node and the routes
+/// all nodes logged. Local nodes are identified through the
+/// `registry` and keep the neighborhood mode of their config.
+pub fn build_topology(nodes: &AllNodesData, registry: &IdentityRegistry) -> NetworkTopology {
+    let mut topology_nodes: BTreeMap<String, TopologyNode> = BTreeMap::new();
+    let mut edges: BTreeMap<(String, String), TopologyEdge> = BTreeMap::new();
+
+    let add_node = |key: &str, nodes_by_key: &mut BTreeMap<String, TopologyNode>| {
+        let key = normalize_key(key);
+        nodes_by_key.entry(key.clone()).or_insert_with(|| {
+            let node = registry.node_for(&key).map(str::to_string);
+            let neighborhood_mode = node
+                .as_ref()
+                .and_then(|name| nodes.get(name)?.config.as_ref()?.neighborhood_mode.clone());
+            TopologyNode {
+                public_key: key.clone(),
+                node,
+                neighborhood_mode,
+                ..Default::default()
+            }
+        });
+        key
+    };
+
+    // Local nodes are shown even before any gossip mentions them
+    for identity in &registry.identities {
+        add_node(&identity.public_key, &mut topology_nodes);
+    }
+
+    let gossip = parse_all_gossip(nodes);
+    let mut sources = Vec::new();
+    for (name, entries) in &gossip {
+        let Some(latest) = entries.iter().rev().find(|e| e.tag == "Current database") else {
+            continue;
+        };
+        sources.push(TopologySource {
+            node: name.clone(),
+            timestamp: latest.timestamp.clone(),
+        });
+        for gossip_node in &latest.graph.nodes {
+            let key = add_node(&gossip_node.public_key, &mut topology_nodes);
+            let node = topology_nodes.get_mut(&key).unwrap();
+            node.merge(gossip_node);
+            if !node.seen_by.contains(name) {
+                node.seen_by.push(name.clone());
+            }
+        }
+        for gossip_edge in &latest.graph.edges {
+            let from = add_node(&gossip_edge.from, &mut topology_nodes);
+            let to = add_node(&gossip_edge.to, &mut topology_nodes);
+            let edge = edges
+                .entry((from.clone(), to.clone()))
+                .or_insert_with(|| TopologyEdge {
+                    from,
+                    to,
+                    seen_by: Vec::new(),
+                    route_count: 0,
+                });
+            if !edge.seen_by.contains(name) {
+                edge.seen_by.push(name.clone());
+            }
+        }
+    }
+
+    let gossip_keys: BTreeSet<String> = topology_nodes.keys().cloned().collect();
+    let route_edges = RouteEdgeCount::aggregate(nodes.values().flat_map(|n| &n.routes));
+    for route_edge in route_edges {
+        let from = resolve_hop(&route_edge.from, &gossip_keys, registry);
+        let to = resolve_hop(&route_edge.to, &gossip_keys, registry);
+        let from = add_node(&from, &mut topology_nodes);
+        let to = add_node(&to, &mut topology_nodes);
+        edges
+            .entry((from.clone(), to.clone()))
+            .or_insert_with(|| TopologyEdge {
+                from,
+                to,
+                seen_by: Vec::new(),
+                route_count: 0,
+            })
+            .route_count += route_edge.count;
+    }
+
+    NetworkTopology {
+        nodes: topology_nodes.into_values().collect(),
+        edges: edges.into_values().collect(),
+        sources,
+    }
+}
+
+/// The full key of a route hop: a known key it equals or, abbreviated to
+/// at least `KEY_PREFIX_LEN` characters, uniquely starts; or the hop itself
+/// if there is none.
+fn resolve_hop(hop: &str, keys: &BTreeSet<String>, registry: &IdentityRegistry) -> String {
+    let hop = normalize_key(hop);
+    if keys.contains(&hop) {
+        return hop;
+    }
+    if hop.len() >= KEY_PREFIX_LEN {
+        let mut matches = keys
+            .range(hop.clone()..)
+            .take_while(|key| key.starts_with(&hop));
+        if let (Some(key), None) = (matches.next(), matches.next()) {
+            return key.clone();
+        }
+    }
+    registry
+        .identity(&hop)
+        .map(|identity| normalize_key(&identity.public_key))
+        .unwrap_or(hop)
+}
@@ -0,0 +1,458 @@
+use crate::database::extract_database_structure;
+use crate::identity::IdentityRegistry;
+use crate::models::{AllNodesData, NodeData};
+use crate::parser::{
+    flat_node_prefix, group_flat_files, is_log_head_line, is_node_dir, parse_content,
+    parse_flat_node, parse_node, read_last_lines, read_node_config,
+};
+use crate::tail::{file_id, FileId};
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Identity, size and modification time of a watched file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    id: FileId,
+    len: u64,
+    mtime_nanos: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let mtime_nanos = metadata
+            .modified()
+            .ok()?
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Some(FileStamp {
+            id: file_id(&metadata),
+            len: metadata.len(),
+            mtime_nanos,
+        })
+    }
+}
+
+/// How far a current log was parsed for routes and gossip.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct LogProgress {
+    /// Bytes handed to `parse_content`
+    parsed: u64,
+    /// Bytes of complete lines read; those past `parsed` are a record held
+    /// back for lines that may still belong to it
+    read: u64,
+    /// Bytes of the log seen, including a half-written last line
+    seen: u64,
+}
+
+impl LogProgress {
+    fn up_to(parsed: u64) -> Self {
+        LogProgress {
+            parsed,
+            read: parsed,
+            seen: parsed,
+        }
+    }
+
+    fn holds_record(&self) -> bool {
+        self.read > self.parsed
+    }
+}
+
+/// Offset of the line the last record of `lines` starts with, or 0 if they
+/// are all part of one record.
+fn last_record_start(lines: &[u8]) -> usize {
+    let mut start = 0;
+    let mut offset = 0;
+    for line in lines.split_inclusive(|&b| b == b'\n') {
+        let text = String::from_utf8_lossy(line);
+        if is_log_head_line(text.trim_end_matches(['\n', '\r'])) {
+            start = offset;
+        }
+        offset += line.len();
+    }
+    start
+}
+
+/// Where a node's files live.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum NodeSource {
+    /// A node directory of the nested structure
+    Dir(String),
+    /// `N-...` files directly in the input directory
+    Flat(String),
+}
+
+impl NodeSource {
+    fn name(&self) -> &str {
+        match self {
+            NodeSource::Dir(name) | NodeSource::Flat(name) => name,
+        }
+    }
+}
+
+/// Files whose changes affect `NodeData`. A running node writes its
+/// database through `node-data.db-wal`, so that changes with it.
+fn is_watched_file(name: &str) -> bool {
+    name.ends_with(".log")
+        || name.ends_with(".zip")
+        || name.ends_with(".db")
+        || name.ends_with(".db-wal")
+        || is_config_file(name)
+}
+
+fn is_config_file(name: &str) -> bool {
+    name.ends_with("config.toml") || name.ends_with("config.del")
+}
+
+fn is_log_file(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| {
+        let name = name.to_string_lossy();
+        name.ends_with(".log") || name.ends_with(".zip")
+    })
+}
+
+fn is_current_log(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().ends_with("MASQNode_rCURRENT.log"))
+}
+
+/// Keeps `AllNodesData` in step with the input directory of a running test
+/// network by polling file sizes and modification times.
+///
+/// Only what changed is re-parsed: lines appended to `MASQNode_rCURRENT.log`
+/// are parsed on their own, a changed database only has its structure
+/// re-extracted, a changed config is read again, and a node is re-parsed as
+/// a whole only when its set of log files changes (new node, rotation,
+/// truncation).
+///
+/// Like `LogTail`, the last record appended is held back until the next head
+/// line arrives, so a multi-line gossip dump written over two polls is
+/// parsed in one piece. It is released anyway once a poll finds nothing new.
+pub struct NodeWatcher {
+    input_dir: PathBuf,
+    files: HashMap<PathBuf, FileStamp>,
+    /// How far each current log was parsed for routes and gossip
+    parsed: HashMap<PathBuf, LogProgress>,
+}
+
+impl NodeWatcher {
+    /// Takes the state of `input_dir` to report changes against. Create the
+    /// watcher before scanning the directory, then hand the scanned nodes to
+    /// `start_from`, so nothing that changes during the scan is missed.
+    pub fn new(input_dir: PathBuf) -> Self {
+        let files = snapshot(&input_dir);
+        NodeWatcher {
+            input_dir,
+            files,
+            parsed: HashMap::new(),
+        }
+    }
+
+    /// Continues each current log from where the scan of `nodes` stopped
+    /// parsing it.
+    pub fn start_from(&mut self, nodes: &AllNodesData) {
+        let nested = self.is_nested();
+        for path in self.files.keys() {
+            if !is_current_log(path) {
+                continue;
+            }
+            let node = self
+                .node_source(path, nested)
+                .and_then(|source| nodes.get(source.name()));
+            if let Some(node) = node {
+                self.parsed
+                    .insert(path.clone(), LogProgress::up_to(node.current_log_parsed));
+            }
+        }
+    }
+
+    /// Applies changes made since the last poll and returns the names of the
+    /// nodes that were added, updated or removed.
+    pub fn poll(&mut self, nodes: &RwLock<AllNodesData>) -> Vec<String> {
+        let files = snapshot(&self.input_dir);
+        let nested = self.is_nested();
+
+        let mut changed: BTreeMap<NodeSource, Vec<PathBuf>> = BTreeMap::new();
+        let paths: BTreeSet<&PathBuf> = self.files.keys().chain(files.keys()).collect();
+        for path in paths {
+            // A record held back last time is released if nothing followed it
+            let unchanged = self.files.get(path) == files.get(path);
+            let held = self.parsed.get(path).is_some_and(|p| p.holds_record());
+            if !unchanged || held {
+                if let Some(source) = self.node_source(path, nested) {
+                    changed.entry(source).or_default().push(path.clone());
+                }
+            }
+        }
+
+        let mut updated = Vec::new();
+        for (source, paths) in changed {
+            let previous = nodes.read().unwrap().get(source.name()).cloned();
+            let existed = previous.is_some();
+            let update = self.update_node(&source, previous, &paths, &files, nested);
+
+            let mut nodes = nodes.write().unwrap();
+            match update {
+                Some(node_data) => {
+                    nodes.insert(source.name().to_string(), node_data);
+                }
+                // Files changed in a directory that is not (or no longer) a node
+                None if !existed => continue,
+                None => {
+                    nodes.remove(source.name());
+                }
+            }
+            updated.push(source.name().to_string());
+        }
+
+        self.parsed.retain(|path, _| files.contains_key(path));
+        self.files = files;
+        updated
+    }
+
+    /// Nodes are read from subdirectories if there are any, otherwise from
+    /// flat `N-...` files, mirroring `scan_directory`.
+    fn is_nested(&self) -> bool {
+        fs::read_dir(&self.input_dir).is_ok_and(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .any(|e| e.path().is_dir() && is_node_dir(&e.path()))
+        })
+    }
+
+    fn node_source(&self, path: &Path, nested: bool) -> Option<NodeSource> {
+        let parent = path.parent()?;
+        if parent == self.input_dir {
+            if nested {
+                return None;
+            }
+            let name = path.file_name()?.to_str()?;
+            flat_node_prefix(name).map(|prefix| NodeSource::Flat(prefix.to_string()))
+        } else {
+            let name = parent.file_name()?.to_string_lossy().to_string();
+            Some(NodeSource::Dir(name))
+        }
+    }
+
+    /// Computes the new state of one node, or `None` if it is gone.
+    fn update_node(
+        &mut self,
+        source: &NodeSource,
+        previous: Option<NodeData>,
+        changed: &[PathBuf],
+        files: &HashMap<PathBuf, FileStamp>,
+        nested: bool,
+    ) -> Option<NodeData> {
+        let incremental = previous.filter(|_| {
+            changed
+                .iter()
+                .all(|path| self.grew(path, files) || !is_log_file(path))
+        });
+
+        let Some(mut node_data) = incremental else {
+            let node_data = self.parse_whole(source);
+            // Carry on from wherever the full parse stopped reading
+            let parsed = node_data.as_ref().map_or(0, |n| n.current_log_parsed);
+            for path in files.keys() {
+                if is_current_log(path) && self.node_source(path, nested).as_ref() == Some(source) {
+                    self.parsed.insert(path.clone(), LogProgress::up_to(parsed));
+                }
+            }
+            return node_data;
+        };
+
+        for path in changed {
+            if is_current_log(path) {
+                if let Err(e) = self.parse_appended(path, &mut node_data) {
+                    eprintln!("Failed to parse new lines of {:?}: {}", path, e);
+                }
+                if matches!(source, NodeSource::Dir(_)) {
+                    if let Ok(current_log) = read_last_lines(path, 1000) {
+                        node_data.current_log = current_log;
+                    }
+                }
+            } else if let Some(name) = path
+                .file_name()
+                .map(|n| n.to_string_lossy())
+                .filter(|n| is_config_file(n))
+            {
+                let config = files
+                    .contains_key(path)
+                    .then(|| read_node_config(path))
+                    .and_then(|config| {
+                        config
+                            .map_err(|e| eprintln!("Failed to read config {:?}: {}", path, e))
+                            .ok()
+                    });
+                if name.ends_with(".del") {
+                    node_data.config_del = config;
+                } else {
+                    node_data.config = config;
+                }
+            } else {
+                // A write-ahead log stands for the database it belongs to
+                let db_path = match path.to_string_lossy().strip_suffix("-wal") {
+                    Some(db_path) if changed.iter().any(|p| p.as_os_str() == db_path) => {
+                        continue;
+                    }
+                    Some(db_path) => PathBuf::from(db_path),
+                    None => path.clone(),
+                };
+                if matches!(source, NodeSource::Dir(_))
+                    && db_path.file_name().is_some_and(|n| n != "node-data.db")
+                {
+                    // Nested nodes only ever read `node-data.db`
+                    continue;
+                } else if files.contains_key(&db_path) {
+                    match extract_database_structure(&db_path) {
+                        Ok(database) => node_data.database = database,
+                        Err(e) => eprintln!("Failed to read database {:?}: {}", db_path, e),
+                    }
+                } else {
+                    node_data.database = Default::default();
+                }
+            }
+        }
+        Some(node_data)
+    }
+
+    /// True if `path` is a current log that only had lines appended: the
+    /// same file as before, no shorter. A log rotated away and replaced by a
+    /// new one that is already as long is a different file.
+    fn grew(&self, path: &Path, files: &HashMap<PathBuf, FileStamp>) -> bool {
+        is_current_log(path)
+            && match (self.files.get(path), files.get(path)) {
+                (Some(old), Some(new)) => new.id == old.id && new.len >= old.len,
+                _ => false,
+            }
+    }
+
+    fn parse_whole(&self, source: &NodeSource) -> Option<NodeData> {
+        match source {
+            NodeSource::Dir(name) => {
+                let node_dir = self.input_dir.join(name);
+                if !is_node_dir(&node_dir) {
+                    return None;
+                }
+                match parse_node(&node_dir) {
+                    Ok(node_data) => Some(node_data),
+                    Err(e) => {
+                        eprintln!("Failed to parse node {}: {}", name, e);
+                        None
+                    }
+                }
+            }
+            NodeSource::Flat(name) => {
+                let paths = fs::read_dir(&self.input_dir)
+                    .map(|entries| {
+                        entries
+                            .filter_map(|e| e.ok().map(|e| e.path()))
+                            .filter(|p| p.is_file())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                group_flat_files(paths)
+                    .remove(name)
+                    .and_then(|files| parse_flat_node(name, &files))
+            }
+        }
+    }
+
+    /// Parses the records appended to a current log since last time,
+    /// holding back the last one unless the poll found nothing new.
+    fn parse_appended(&mut self, path: &Path, node_data: &mut NodeData) -> Result<()> {
+        let progress = self.parsed.get(path).copied().unwrap_or_default();
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(progress.parsed))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+
+        // A half-written last line is left for the next poll
+        let complete = bytes
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |end| end + 1);
+        let read = progress.parsed + complete as u64;
+        let seen = progress.parsed + bytes.len() as u64;
+        let partial = String::from_utf8_lossy(&bytes[complete..]);
+        let end = if seen == progress.seen || is_log_head_line(&partial) {
+            // Nothing new arrived, or a new record already began
+            complete
+        } else {
+            last_record_start(&bytes[..complete])
+        };
+
+        if end > 0 {
+            parse_content(&String::from_utf8_lossy(&bytes[..end]), node_data);
+        }
+        self.parsed.insert(
+            path.to_path_buf(),
+            LogProgress {
+                parsed: progress.parsed + end as u64,
+                read,
+                seen,
+            },
+        );
+        Ok(())
+    }
+}
+
+/// Stamps of all watched files in the input directory and its node dirs.
+fn snapshot(input_dir: &Path) -> HashMap<PathBuf, FileStamp> {
+    let mut files = HashMap::new();
+    let Ok(entries) = fs::read_dir(input_dir) else {
+        return files;
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            if let Ok(node_entries) = fs::read_dir(&path) {
+                for node_entry in node_entries.filter_map(|e| e.ok()) {
+                    add_stamp(&mut files, node_entry.path());
+                }
+            }
+        } else {
+            add_stamp(&mut files, path);
+        }
+    }
+    files
+}
+
+fn add_stamp(files: &mut HashMap<PathBuf, FileStamp>, path: PathBuf) {
+    let watched = path
+        .file_name()
+        .is_some_and(|name| is_watched_file(&name.to_string_lossy()));
+    if watched && path.is_file() {
+        if let Some(stamp) = FileStamp::of(&path) {
+            files.insert(path, stamp);
+        }
+    }
+}
+
+/// Polls every `interval` on a background thread, updating `nodes_data` in
+/// place and rebuilding `identities` from it when a node changed.
+pub fn spawn_watcher(
+    mut watcher: NodeWatcher,
+    nodes_data: Arc<RwLock<AllNodesData>>,
+    identities: Arc<RwLock<IdentityRegistry>>,
+    interval: Duration,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        let updated = watcher.poll(&nodes_data);
+        if !updated.is_empty() {
+            println!("Updated nodes: {}", updated.join(", "));
+            let registry = IdentityRegistry::build(&nodes_data.read().unwrap());
+            *identities.write().unwrap() = registry;
+        }
+    })
+}
@@ -0,0 +1,279 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::routing::get;
+use masq_log_visualizer::accounting::{
+    accounting_report, payment_threshold, ReconciliationStatus, Wei,
+};
+use masq_log_visualizer::models::{AllNodesData, NodeConfig, NodeData, PaymentThresholds};
+use masq_log_visualizer::parser::scan_directory;
+use masq_log_visualizer::routes::get_accounting;
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+use tower::ServiceExt;
+
+mod common;
+use common::app_state;
+
+const MASQ: i64 = 1_000_000_000_000_000_000;
+const AT: i64 = 1_700_000_000;
+
+const THRESHOLDS: PaymentThresholds = PaymentThresholds {
+    debt_threshold_gwei: 1_000_000_000,
+    maturity_threshold_sec: 1200,
+    payment_grace_period_sec: 1200,
+    permanent_debt_allowed_gwei: 500_000_000,
+    threshold_interval_sec: 21600,
+    unban_below_gwei: 500_000_000,
+};
+
+/// A node database of `schema_version`. Payables are (wallet, wei, last paid,
+/// pending payable rowid), receivables (wallet, wei, last received).
+fn create_node_database(
+    path: &Path,
+    schema_version: &str,
+    earning_wallet: Option<&str>,
+    payables: &[(&str, i64, i64, Option<i64>)],
+    receivables: &[(&str, i64, i64)],
+) {
+    let conn = Connection::open(path).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE config (name TEXT, value TEXT, encrypted INTEGER);
+         CREATE TABLE payable (wallet_address TEXT, balance_high_b INTEGER, balance_low_b INTEGER,
+             last_paid_timestamp INTEGER, pending_payable_rowid INTEGER);
+         CREATE TABLE receivable (wallet_address TEXT, balance_high_b INTEGER, balance_low_b INTEGER,
+             last_received_timestamp INTEGER);",
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO config VALUES ('schema_version', ?1, 0), ('earning_wallet_address', ?2, 0)",
+        (schema_version, earning_wallet),
+    )
+    .unwrap();
+    for (wallet, wei, last_paid, pending) in payables {
+        conn.execute(
+            "INSERT INTO payable VALUES (?1, 0, ?2, ?3, ?4)",
+            (wallet, wei, last_paid, pending),
+        )
+        .unwrap();
+    }
+    for (wallet, wei, last_received) in receivables {
+        conn.execute(
+            "INSERT INTO receivable VALUES (?1, 0, ?2, ?3)",
+            (wallet, wei, last_received),
+        )
+        .unwrap();
+    }
+}
+
+fn node(name: &str, earning: &[&str], consuming: &[&str], config: Option<NodeConfig>) -> NodeData {
+    NodeData {
+        earning_wallets: earning.iter().map(|w| w.to_string()).collect(),
+        consuming_wallets: consuming.iter().map(|w| w.to_string()).collect(),
+        config,
+        ..NodeData::new(name)
+    }
+}
+
+/// node_a owes node_b and node_c, node_c owes node_a and node_b. node_b's
+/// earning wallet is only configured, node_c's only in its database.
+fn network(dir: &Path) -> AllNodesData {
+    let config_a = NodeConfig {
+        payment_thresholds: Some(THRESHOLDS),
+        ..Default::default()
+    };
+    let mut config_b = NodeConfig::default();
+    config_b
+        .entries
+        .insert("earning-wallet".to_string(), "0xB0E".to_string());
+
+    create_node_database(
+        &dir.join("node_a-node-data.db"),
+        "9",
+        None,
+        &[
+            ("0xb0e", 2 * MASQ, AT - 7200, None),
+            ("0xC0E", MASQ, AT - 86400, Some(1)),
+            ("0xdead", MASQ, AT, None),
+        ],
+        &[],
+    );
+    std::fs::create_dir(dir.join("node_b")).unwrap();
+    create_node_database(
+        &dir.join("node_b").join("node-data.db"),
+        "9",
+        None,
+        &[],
+        &[("0xA1C", 2 * MASQ, AT - 7200), ("0xc0c", MASQ / 4, AT)],
+    );
+    create_node_database(
+        &dir.join("node_c-node-data.db"),
+        "10",
+        Some("0xc0e"),
+        &[("0xa1e", MASQ / 10, AT - 60, None)],
+        &[("0xa1c", MASQ / 2, AT - 86400)],
+    );
+    create_node_database(&dir.join("node_e-node-data.db"), "3", None, &[], &[]);
+
+    common::network([
+        node("node_a", &["0xa1e"], &["0xa1c"], Some(config_a)),
+        node("node_b", &[], &["0xb0c"], Some(config_b)),
+        node("node_c", &[], &["0xc0c"], None),
+        node("node_d", &[], &[], None),
+        node("node_e", &[], &[], None),
+    ])
+}
+
+#[test]
+fn test_payment_threshold() {
+    let gwei = 1_000_000_000;
+    assert_eq!(payment_threshold(&THRESHOLDS, 0), None);
+    assert_eq!(payment_threshold(&THRESHOLDS, 1200), None);
+    assert_eq!(
+        payment_threshold(&THRESHOLDS, 1200 + 10800),
+        Some(750_000_000 * gwei)
+    );
+    assert_eq!(
+        payment_threshold(&THRESHOLDS, 1200 + 21600),
+        Some(500_000_000 * gwei)
+    );
+    assert_eq!(
+        payment_threshold(&THRESHOLDS, i64::MAX),
+        Some(500_000_000 * gwei)
+    );
+    let immediate = PaymentThresholds {
+        threshold_interval_sec: 0,
+        ..THRESHOLDS
+    };
+    assert_eq!(
+        payment_threshold(&immediate, 1201),
+        Some(500_000_000 * gwei)
+    );
+}
+
+#[test]
+fn test_reconciliation() {
+    let dir = tempfile::tempdir().unwrap();
+    let nodes = network(dir.path());
+    let report = accounting_report(&nodes, dir.path(), AT);
+    assert_eq!(report.at, AT);
+
+    let wallets = &report.wallets[1];
+    assert_eq!(wallets.node, "node_b");
+    assert_eq!(wallets.earning, ["0xB0E"]);
+    assert_eq!(report.wallets[2].earning, ["0xc0e"]);
+
+    let pairs: Vec<(&str, &str, ReconciliationStatus)> = report
+        .debts
+        .iter()
+        .map(|d| (d.debtor.as_str(), d.creditor.as_str(), d.status))
+        .collect();
+    assert_eq!(
+        pairs,
+        [
+            ("node_a", "node_b", ReconciliationStatus::Matched),
+            ("node_a", "node_c", ReconciliationStatus::Mismatched),
+            ("node_c", "node_a", ReconciliationStatus::MissingReceivable),
+            ("node_c", "node_b", ReconciliationStatus::MissingPayable),
+        ]
+    );
+
+    // Two hours after the last payment the 2 MASQ exceed node_a's threshold
+    let a_to_b = &report.debts[0];
+    assert_eq!(a_to_b.payable, Some(Wei(2 * MASQ as i128)));
+    assert_eq!(a_to_b.difference, Some(Wei(0)));
+    assert_eq!(a_to_b.creditor_wallets, ["0xb0e"]);
+    assert_eq!(a_to_b.debtor_wallets, ["0xA1C"]);
+    assert_eq!(
+        a_to_b.threshold,
+        payment_threshold(&THRESHOLDS, 7200).map(Wei)
+    );
+    assert!(a_to_b.overdue);
+
+    // Past the threshold too, but a payment is on its way
+    let a_to_c = &report.debts[1];
+    assert_eq!(a_to_c.difference, Some(Wei(MASQ as i128 / 2)));
+    assert!(a_to_c.payment_pending);
+    assert!(!a_to_c.overdue);
+
+    // node_c has no payment thresholds configured
+    let c_to_a = &report.debts[2];
+    assert_eq!(c_to_a.receivable, None);
+    assert_eq!(c_to_a.threshold, None);
+    assert!(!c_to_a.overdue);
+
+    let c_to_b = &report.debts[3];
+    assert_eq!(c_to_b.payable, None);
+    assert_eq!(c_to_b.last_received, Some(AT));
+
+    assert_eq!(report.unmatched.len(), 1);
+    assert_eq!(report.unmatched[0].node, "node_a");
+    assert_eq!(report.unmatched[0].wallet, "0xdead");
+
+    let skipped: Vec<&str> = report.skipped.iter().map(|s| s.node.as_str()).collect();
+    assert_eq!(skipped, ["node_d", "node_e"]);
+    assert!(report.skipped[1].reason.contains("schema version 3"));
+
+    // Before node_a's debt to node_b matured nothing is overdue
+    let earlier = accounting_report(&nodes, dir.path(), AT - 6600);
+    assert_eq!(earlier.debts[0].threshold, None);
+    assert!(!earlier.debts[0].overdue);
+}
+
+#[tokio::test]
+async fn test_accounting_route() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = app_state(network(dir.path()), dir.path());
+    let app = axum::Router::new()
+        .route("/api/accounting", get(get_accounting))
+        .with_state(state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/accounting?at={}", AT))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["at"], AT);
+    let debt = &json["debts"][0];
+    assert_eq!(debt["status"], "matched");
+    assert_eq!(debt["overdue"], true);
+    assert_eq!(debt["payable"]["wei"], "2000000000000000000");
+    assert_eq!(debt["payable"]["masq"], "2 MASQ");
+    assert_eq!(json["debts"][2]["status"], "missingReceivable");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/accounting?at=soon")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn test_wallets_of_example_log() {
+    let test_dir = PathBuf::from("./Example-Log");
+    if !test_dir.exists() {
+        return;
+    }
+    let nodes = scan_directory(&test_dir).unwrap();
+    assert!(nodes.values().any(|n| !n.earning_wallets.is_empty()));
+    assert!(nodes.values().any(|n| !n.consuming_wallets.is_empty()));
+    for node in nodes.values() {
+        for wallet in node.earning_wallets.iter().chain(&node.consuming_wallets) {
+            assert!(wallet.starts_with("0x"), "{}: {}", node.name, wallet);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! Fixtures shared by the integration tests. Each test crate uses only some
+//! of them.
+#![allow(dead_code)]
+
+use masq_log_visualizer::identity::IdentityRegistry;
+use masq_log_visualizer::models::{AllNodesData, GossipEntry, NodeData, NodeDescriptor};
+use masq_log_visualizer::parser::parse_node_descriptor;
+use masq_log_visualizer::routes::AppState;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+/// A gossip entry the Neighborhood logged at `timestamp`.
+pub fn gossip_at(timestamp: &str, tag: &str, dot: impl Into<String>) -> GossipEntry {
+    GossipEntry {
+        timestamp: timestamp.to_string(),
+        actor: "Neighborhood".to_string(),
+        tag: tag.to_string(),
+        dot: dot.into(),
+    }
+}
+
+/// A gossip entry logged `second` seconds after 2025-02-03 20:00:00.
+pub fn gossip(second: u32, tag: &str, dot: impl Into<String>) -> GossipEntry {
+    gossip_at(&format!("2025-02-03 20:00:{:02}.000", second), tag, dot)
+}
+
+/// The local descriptor of a node whose public key is `key`.
+pub fn descriptor(key: &str) -> Vec<NodeDescriptor> {
+    parse_node_descriptor(&format!("masq://polygon-amoy:{}@:", key))
+        .into_iter()
+        .collect()
+}
+
+/// A node that logged `gossip` and nothing else.
+pub fn node(name: &str, gossip: Vec<GossipEntry>) -> NodeData {
+    NodeData {
+        gossip,
+        ..NodeData::new(name)
+    }
+}
+
+/// All `nodes`, keyed by name.
+pub fn network(nodes: impl IntoIterator<Item = NodeData>) -> AllNodesData {
+    nodes
+        .into_iter()
+        .map(|node| (node.name.clone(), node))
+        .collect()
+}
+
+/// The state the routes run on, serving `nodes` from `input_dir`.
+pub fn app_state(nodes: AllNodesData, input_dir: &Path) -> AppState {
+    let identities = IdentityRegistry::build(&nodes);
+    AppState {
+        nodes_data: Arc::new(RwLock::new(nodes)),
+        input_dir: input_dir.to_path_buf(),
+        tera: Arc::new(tera::Tera::default()),
+        log_index: Default::default(),
+        redactor: Default::default(),
+        identities: Arc::new(RwLock::new(identities)),
+    }
+}
@@ -0,0 +1,467 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::routing::{get, post};
+use masq_log_visualizer::database::{
+    get_table_data, open_read_only, query_table, run_query, table_names, ColumnFilter, FilterOp,
+    InvalidQuery, TableQuery, UnknownTable,
+};
+use masq_log_visualizer::parser::scan_directory;
+use masq_log_visualizer::redact::{Redactor, REDACTED};
+use masq_log_visualizer::routes::{get_db_table_data, post_db_query, AppState};
+use rusqlite::Connection;
+use serde_json::json;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tower::ServiceExt;
+
+fn create_database(path: &Path) {
+    let conn = Connection::open(path).unwrap();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE payable (wallet_address TEXT, balance_high_b INTEGER);
+        INSERT INTO payable VALUES ('0x01', 100), ('0x02', 200);
+        CREATE TABLE "odd ""name""; --" (value TEXT);
+        INSERT INTO "odd ""name""; --" VALUES ('kept');
+        "#,
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_table_names_are_validated() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("node-data.db");
+    create_database(&db_path);
+
+    for table_name in [
+        "payable; DROP TABLE payable",
+        "payable\" WHERE 1=1 --",
+        "sqlite_master",
+        "missing",
+    ] {
+        let err = get_table_data(&db_path, table_name).unwrap_err();
+        assert!(err.is::<UnknownTable>(), "{}", table_name);
+    }
+
+    let payable = get_table_data(&db_path, "payable").unwrap();
+    assert_eq!(payable.columns, ["wallet_address", "balance_high_b"]);
+    assert_eq!(payable.rows.len(), 2);
+
+    let odd = get_table_data(&db_path, "odd \"name\"; --").unwrap();
+    assert_eq!(odd.columns, ["value"]);
+    assert_eq!(odd.rows, [vec![serde_json::json!("kept")]]);
+}
+
+#[test]
+fn test_databases_are_never_written() {
+    let dir = tempfile::tempdir().unwrap();
+    let node_dir = dir.path().join("node_a");
+    std::fs::create_dir(&node_dir).unwrap();
+    std::fs::write(node_dir.join("MASQNode_rCURRENT.log"), "").unwrap();
+    let db_path = node_dir.join("node-data.db");
+    create_database(&db_path);
+
+    let conn = open_read_only(&db_path).unwrap();
+    assert!(conn.execute("DELETE FROM payable", []).is_err());
+    drop(conn);
+
+    let nodes = scan_directory(dir.path()).unwrap();
+    assert_eq!(nodes["node_a"].database.tables.len(), 2);
+    get_table_data(&db_path, "payable").unwrap();
+
+    let mut files: Vec<String> = std::fs::read_dir(&node_dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    files.sort();
+    assert_eq!(files, ["MASQNode_rCURRENT.log", "node-data.db"]);
+}
+
+#[test]
+fn test_locked_database_is_read_as_immutable() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("node-data.db");
+    create_database(&db_path);
+
+    // A running node in the middle of a write
+    let node = Connection::open(&db_path).unwrap();
+    node.execute_batch("BEGIN EXCLUSIVE; INSERT INTO payable VALUES ('0x03', 300);")
+        .unwrap();
+
+    let conn = open_read_only(&db_path).unwrap();
+    assert_eq!(table_names(&conn).unwrap(), ["odd \"name\"; --", "payable"]);
+    assert_eq!(get_table_data(&db_path, "payable").unwrap().rows.len(), 2);
+
+    node.execute_batch("ROLLBACK").unwrap();
+}
+
+/// 250 payables: wallets `0x000` to `0x249`, balance ten times the number,
+/// every tenth with a `%` in its note
+fn create_payables(path: &Path) {
+    let conn = Connection::open(path).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE payable (wallet_address TEXT, balance INTEGER, note TEXT, seed TEXT)",
+    )
+    .unwrap();
+    for i in 0..250 {
+        let note = if i % 10 == 0 { "100% paid" } else { "pending" };
+        conn.execute(
+            "INSERT INTO payable VALUES (?1, ?2, ?3, 'secret')",
+            rusqlite::params![format!("0x{:03}", i), i * 10, note],
+        )
+        .unwrap();
+    }
+}
+
+fn wallets(rows: &[Vec<serde_json::Value>]) -> Vec<&str> {
+    rows.iter().map(|row| row[0].as_str().unwrap()).collect()
+}
+
+#[test]
+fn test_query_table() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("node-data.db");
+    create_payables(&db_path);
+    let redactor = Redactor::default();
+    let filter = |column: &str, op, value: &str| ColumnFilter {
+        column: column.to_string(),
+        op,
+        value: value.to_string(),
+    };
+
+    let page = query_table(
+        &db_path,
+        "payable",
+        &TableQuery {
+            offset: 10,
+            limit: Some(3),
+            order_by: Some("balance".to_string()),
+            desc: true,
+            ..Default::default()
+        },
+        &redactor,
+    )
+    .unwrap();
+    assert_eq!(page.total_rows, 250);
+    assert_eq!(page.offset, 10);
+    assert_eq!(wallets(&page.table.rows), ["0x239", "0x238", "0x237"]);
+
+    let page = query_table(
+        &db_path,
+        "payable",
+        &TableQuery {
+            filters: vec![
+                filter("balance", FilterOp::Min, "1000"),
+                filter("balance", FilterOp::Max, "1200"),
+                filter("wallet_address", FilterOp::Like, "%0"),
+            ],
+            ..Default::default()
+        },
+        &redactor,
+    )
+    .unwrap();
+    assert_eq!(page.total_rows, 3);
+    assert_eq!(wallets(&page.table.rows), ["0x100", "0x110", "0x120"]);
+
+    // Numbers compare as numbers, text as text
+    let eq = |column: &str, value: &str| {
+        query_table(
+            &db_path,
+            "payable",
+            &TableQuery {
+                filters: vec![filter(column, FilterOp::Eq, value)],
+                ..Default::default()
+            },
+            &redactor,
+        )
+        .unwrap()
+        .total_rows
+    };
+    assert_eq!(eq("balance", "50"), 1);
+    assert_eq!(eq("balance", "50.0"), 1);
+    assert_eq!(eq("wallet_address", "0x005"), 1);
+    assert_eq!(eq("wallet_address", "0x005' OR '1'='1"), 0);
+
+    // Search wildcards are literal
+    let search = |text: &str| {
+        query_table(
+            &db_path,
+            "payable",
+            &TableQuery {
+                search: Some(text.to_string()),
+                ..Default::default()
+            },
+            &redactor,
+        )
+        .unwrap()
+        .total_rows
+    };
+    assert_eq!(search("0% PAID"), 25);
+    assert_eq!(search("%"), 25);
+    assert_eq!(search("_"), 0);
+    // Redacted columns are not searched
+    assert_eq!(search("secret"), 0);
+
+    for query in [
+        TableQuery {
+            order_by: Some("balance DESC; --".to_string()),
+            ..Default::default()
+        },
+        TableQuery {
+            filters: vec![filter("missing", FilterOp::Eq, "1")],
+            ..Default::default()
+        },
+        TableQuery {
+            filters: vec![filter("seed", FilterOp::Like, "s%")],
+            ..Default::default()
+        },
+    ] {
+        let err = query_table(&db_path, "payable", &query, &redactor).unwrap_err();
+        assert!(err.is::<InvalidQuery>(), "{:?}", query);
+    }
+    assert_eq!(
+        query_table(
+            &db_path,
+            "payable",
+            &TableQuery {
+                search: Some("secret".to_string()),
+                ..Default::default()
+            },
+            &Redactor::disabled(),
+        )
+        .unwrap()
+        .total_rows,
+        250
+    );
+}
+
+#[tokio::test]
+async fn test_db_table_data_route() {
+    let dir = tempfile::tempdir().unwrap();
+    create_payables(&dir.path().join("node_a-node-data.db"));
+    let state = AppState {
+        nodes_data: Arc::new(RwLock::new(Default::default())),
+        input_dir: dir.path().to_path_buf(),
+        tera: Arc::new(tera::Tera::default()),
+        log_index: Default::default(),
+        redactor: Default::default(),
+        identities: Default::default(),
+    };
+    let app = axum::Router::new()
+        .route("/api/db/:node_name/:table_name", get(get_db_table_data))
+        .with_state(state);
+    let get_uri = |uri: &str| {
+        app.clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+    };
+
+    // One default-sized page
+    let response = get_uri("/api/db/node_a/payable").await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["totalRows"], 250);
+    assert_eq!(json["rows"].as_array().unwrap().len(), 100);
+    assert_eq!(json["rows"][0][3], REDACTED);
+
+    let response = get_uri(
+        "/api/db/node_a/payable?offset=1&limit=2&order_by=balance&desc=true&min.balance=100&like.note=100%25%25",
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["totalRows"], 24);
+    assert_eq!(json["offset"], 1);
+    assert_eq!(json["rows"][0][0], json!("0x230"));
+    assert_eq!(json["rows"].as_array().unwrap().len(), 2);
+
+    for (uri, status) in [
+        (
+            "/api/db/node_a/payable?order_by=nope",
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            "/api/db/node_a/payable?eq.seed=secret",
+            StatusCode::BAD_REQUEST,
+        ),
+        ("/api/db/node_a/payable?limit=ten", StatusCode::BAD_REQUEST),
+        ("/api/db/node_a/receivable", StatusCode::NOT_FOUND),
+        ("/api/db/node_b/payable", StatusCode::NOT_FOUND),
+    ] {
+        assert_eq!(get_uri(uri).await.unwrap().status(), status, "{}", uri);
+    }
+}
+
+#[test]
+fn test_run_query() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("node-data.db");
+    create_payables(&db_path);
+    Connection::open(&db_path)
+        .unwrap()
+        .execute_batch(
+            "CREATE TABLE config (name TEXT, value TEXT);
+             INSERT INTO config VALUES ('schema_version', '10'), ('seed', 'abcdef');",
+        )
+        .unwrap();
+    let redactor = Redactor::default();
+    let run = |sql: &str, limit| run_query(&db_path, sql, limit, Duration::from_secs(5), &redactor);
+
+    let result = run(
+        "SELECT note, count(*) AS payables, sum(balance) FROM payable GROUP BY note ORDER BY note",
+        10,
+    )
+    .unwrap();
+    assert_eq!(result.table.columns, ["note", "payables", "sum(balance)"]);
+    assert_eq!(
+        result.table.rows,
+        [
+            vec![json!("100% paid"), json!(25), json!(30000)],
+            vec![json!("pending"), json!(225), json!(281250)],
+        ]
+    );
+    assert!(!result.truncated);
+
+    let result = run("SELECT * FROM payable", 5).unwrap();
+    assert_eq!(result.table.rows.len(), 5);
+    assert!(result.truncated);
+    // Redacted columns read as NULL, however they are named or compared
+    assert_eq!(result.table.rows[0][3], json!(null));
+    for sql in [
+        "SELECT seed AS s FROM payable LIMIT 1",
+        "SELECT max(value) FROM config",
+        "SELECT count(*) FROM config WHERE value = 'abcdef'",
+        "SELECT count(*) FROM payable WHERE seed = 'secret'",
+    ] {
+        let rows = run(sql, 10).unwrap().table.rows;
+        assert!(
+            rows[0][0].is_null() || rows[0][0] == json!(0),
+            "{}: {:?}",
+            sql,
+            rows
+        );
+    }
+    assert_eq!(
+        run_query(
+            &db_path,
+            "SELECT value FROM config WHERE name = 'seed'",
+            10,
+            Duration::from_secs(5),
+            &Redactor::disabled(),
+        )
+        .unwrap()
+        .table
+        .rows,
+        [vec![json!("abcdef")]]
+    );
+
+    // Schema pragmas are reads
+    assert_eq!(
+        run("PRAGMA table_info(payable)", 10)
+            .unwrap()
+            .table
+            .rows
+            .len(),
+        4
+    );
+    assert_eq!(
+        run("SELECT name FROM pragma_table_info('payable')", 10)
+            .unwrap()
+            .table
+            .rows
+            .len(),
+        4
+    );
+
+    let attached = dir.path().join("attached.db");
+    for sql in [
+        "DELETE FROM payable",
+        "UPDATE payable SET balance = 0",
+        "CREATE TABLE t (x)",
+        "DROP TABLE payable",
+        &format!("ATTACH '{}' AS other", attached.display()),
+        "PRAGMA query_only = 0",
+        "PRAGMA journal_mode = DELETE",
+        "BEGIN",
+        "SELECT 1; DROP TABLE payable",
+        "SELECT * FROM missing",
+        "",
+    ] {
+        let err = run(sql, 10).unwrap_err();
+        assert!(err.is::<InvalidQuery>(), "{}: {}", sql, err);
+    }
+    assert!(!attached.exists());
+    assert_eq!(get_table_data(&db_path, "payable").unwrap().rows.len(), 250);
+
+    let err = run_query(
+        &db_path,
+        "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n) SELECT count(*) FROM n",
+        10,
+        Duration::from_millis(200),
+        &redactor,
+    )
+    .unwrap_err();
+    assert!(err.is::<InvalidQuery>());
+    assert_eq!(err.to_string(), "query ran longer than 200 ms");
+}
+
+#[tokio::test]
+async fn test_db_query_route() {
+    let dir = tempfile::tempdir().unwrap();
+    create_payables(&dir.path().join("node_a-node-data.db"));
+    let state = AppState {
+        nodes_data: Arc::new(RwLock::new(Default::default())),
+        input_dir: dir.path().to_path_buf(),
+        tera: Arc::new(tera::Tera::default()),
+        log_index: Default::default(),
+        redactor: Default::default(),
+        identities: Default::default(),
+    };
+    let app = axum::Router::new()
+        .route("/api/db/:node_name/query", post(post_db_query))
+        .with_state(state);
+    let query = |node: &str, body: serde_json::Value| {
+        app.clone().oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/db/{}/query", node))
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+    };
+
+    let response = query(
+        "node_a",
+        json!({ "sql": "SELECT wallet_address, seed FROM payable ORDER BY balance DESC", "limit": 2 }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["columns"], json!(["wallet_address", "seed"]));
+    assert_eq!(json["rows"], json!([["0x249", null], ["0x248", null]]));
+    assert_eq!(json["truncated"], true);
+
+    let response = query("node_a", json!({ "sql": "DELETE FROM payable" }))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(String::from_utf8_lossy(&body).contains("only SELECT statements"));
+
+    let response = query("node_b", json!({ "sql": "SELECT 1" })).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -1,6 +1,6 @@
 // Test to verify template context has correct currentLogFile
 use masq_log_visualizer::parser::scan_directory;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 
 #[test]
 fn test_current_log_file_extraction() {
@@ -0,0 +1,138 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use masq_log_visualizer::dot::parse_gossip_graph;
+use masq_log_visualizer::models::{AllNodesData, GossipEntry, NodeData, RatePack};
+use masq_log_visualizer::routes::{get_gossip_graph, AppState};
+use std::sync::{Arc, RwLock};
+
+/// A `Current database` dump as written by the Neighborhood
+const MASQ_DUMP: &str = r#"digraph db { "gGh8tSmhh9IMrZ494SiSCTNUusOEiLYayfJjVY5pOwE" [label="AR v2 US\ngGh8tSmh\n45.76.63.46:48726"] [style=filled]; "Q0uD8p6ZCNLgM2cBBvsDtDJ8mSG0WBfQ+uDjbW5H3xk" [label="Ar v1 FR\n8615000000|8615000000|17230000000|17230000000\nQ0uD8p6Z\n108.61.189.64:48150/48151"]; "WnK4vpmkL1lCMtV2MaNGhaU8KQVHLg5dGqGdM7vVL4E" [label="WnK4vpmk"] [shape=box]; "gGh8tSmhh9IMrZ494SiSCTNUusOEiLYayfJjVY5pOwE" -> "Q0uD8p6ZCNLgM2cBBvsDtDJ8mSG0WBfQ+uDjbW5H3xk"; "Q0uD8p6ZCNLgM2cBBvsDtDJ8mSG0WBfQ+uDjbW5H3xk" -> "gGh8tSmhh9IMrZ494SiSCTNUusOEiLYayfJjVY5pOwE"; "Q0uD8p6ZCNLgM2cBBvsDtDJ8mSG0WBfQ+uDjbW5H3xk" -> "ZZZ" }"#;
+
+#[test]
+fn test_parse_masq_gossip_dump() {
+    let graph = parse_gossip_graph(MASQ_DUMP).unwrap();
+    assert_eq!(graph.nodes.len(), 4);
+    assert_eq!(graph.edges.len(), 3);
+
+    let source = graph
+        .node("gGh8tSmhh9IMrZ494SiSCTNUusOEiLYayfJjVY5pOwE")
+        .unwrap();
+    assert!(source.known_source);
+    assert!(source.declared);
+    assert_eq!(source.accepts_connections, Some(true));
+    assert_eq!(source.routes_data, Some(true));
+    assert_eq!(source.version, Some(2));
+    assert_eq!(source.country_code.as_deref(), Some("US"));
+    assert_eq!(source.ip.as_deref(), Some("45.76.63.46"));
+    assert_eq!(source.ports, [48726]);
+    assert_eq!(source.label, ["AR v2 US", "gGh8tSmh", "45.76.63.46:48726"]);
+
+    let neighbor = &graph.nodes[1];
+    assert_eq!(neighbor.routes_data, Some(false));
+    assert_eq!(neighbor.ports, [48150, 48151]);
+    assert_eq!(
+        neighbor.rate_pack,
+        Some(RatePack {
+            routing_byte_rate: 8615000000,
+            routing_service_rate: 8615000000,
+            exit_byte_rate: 17230000000,
+            exit_service_rate: 17230000000,
+        })
+    );
+    assert!(!neighbor.known_source);
+
+    let target = &graph.nodes[2];
+    assert!(target.known_target);
+    assert_eq!(target.version, None);
+    assert_eq!(target.ip, None);
+
+    // Only known as the end of an edge
+    assert_eq!(graph.nodes[3].public_key, "ZZZ");
+    assert!(!graph.nodes[3].declared);
+}
+
+#[test]
+fn test_parse_plain_dot() {
+    let graph = parse_gossip_graph(
+        "strict digraph G {\n  // layout\n  rankdir=LR;\n  node [shape=circle];\n  a -> b -> c [color=red];\n  /* nested */ subgraph s { d }\n  \"e \\\"quoted\\\"\";\n}",
+    )
+    .unwrap();
+    let keys: Vec<&str> = graph.nodes.iter().map(|n| n.public_key.as_str()).collect();
+    assert_eq!(keys, ["a", "b", "c", "d", "e \"quoted\""]);
+    assert_eq!(graph.edges.len(), 2);
+    assert_eq!(graph.edges[1].from, "b");
+    assert_eq!(graph.edges[1].attributes["color"], "red");
+
+    assert!(parse_gossip_graph("digraph db { }")
+        .unwrap()
+        .nodes
+        .is_empty());
+    assert!(parse_gossip_graph("digraph db { \"a\" -> }").is_err());
+    assert!(parse_gossip_graph("digraph db { \"a").is_err());
+    assert!(parse_gossip_graph("digraph db { a } extra").is_err());
+    assert!(parse_gossip_graph("not a graph").is_err());
+}
+
+#[tokio::test]
+async fn test_gossip_graph_route() {
+    let mut nodes = AllNodesData::new();
+    nodes.insert(
+        "node_1".to_string(),
+        NodeData {
+            name: "node_1".to_string(),
+            gossip: vec![
+                GossipEntry {
+                    timestamp: "2025-02-03 20:00:01.000".to_string(),
+                    actor: "Neighborhood".to_string(),
+                    tag: "Current database".to_string(),
+                    dot: MASQ_DUMP.to_string(),
+                },
+                GossipEntry {
+                    timestamp: "2025-02-03 20:00:02.000".to_string(),
+                    actor: "Neighborhood".to_string(),
+                    tag: "Sent Gossip".to_string(),
+                    dot: "digraph db { \"a\" -> }".to_string(),
+                },
+            ],
+            ..Default::default()
+        },
+    );
+    let state = AppState {
+        nodes_data: Arc::new(RwLock::new(nodes)),
+        input_dir: std::env::temp_dir(),
+        tera: Arc::new(tera::Tera::default()),
+        log_index: Default::default(),
+        redactor: Default::default(),
+        identities: Default::default(),
+    };
+
+    let request = |node: &str, index: usize| {
+        get_gossip_graph(State(state.clone()), Path((node.to_string(), index)))
+    };
+
+    let response = request("node_1", 0).await.into_response();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["nodes"].as_array().unwrap().len(), 4);
+    assert_eq!(json["nodes"][0]["acceptsConnections"], true);
+    assert_eq!(json["nodes"][1]["ratePack"]["exitByteRate"], 17230000000u64);
+    assert_eq!(json["edges"][0]["to"], json["nodes"][1]["publicKey"]);
+
+    let status = |r: axum::response::Response| r.status();
+    assert_eq!(
+        status(request("node_1", 1).await.into_response()),
+        StatusCode::UNPROCESSABLE_ENTITY
+    );
+    assert_eq!(
+        status(request("node_1", 2).await.into_response()),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        status(request("node_9", 0).await.into_response()),
+        StatusCode::NOT_FOUND
+    );
+}
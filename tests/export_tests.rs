@@ -0,0 +1,181 @@
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::routing::get;
+use masq_log_visualizer::export::{
+    entry_range, export_entry, export_file_name, write_entries, ExportFormat,
+};
+use masq_log_visualizer::identity::IdentityRegistry;
+use masq_log_visualizer::models::{AllNodesData, NodeData};
+use masq_log_visualizer::routes::{get_gossip_export, get_gossip_export_range};
+use std::io::{Cursor, Read};
+use tower::ServiceExt;
+
+mod common;
+use common::{app_state, descriptor, gossip, node};
+
+const DUMP: &str = r#"digraph db { "AAAAAAAAaaaa" [label="AR v1 US\nAAAAAAAAaaaa\n1.1.1.1:1000"] [style=filled]; "BBBBBBBBbbbb" [label="AR v2 FR\nBBBBBBBBbbbb"]; "CCCCCCCC<&>" [label="Ar v1 DE\nCCCCCCCC<&>"]; "AAAAAAAAaaaa" -> "BBBBBBBBbbbb"; "BBBBBBBBbbbb" -> "AAAAAAAAaaaa"; "BBBBBBBBbbbb" -> "CCCCCCCC<&>" }"#;
+
+fn network() -> AllNodesData {
+    common::network([NodeData {
+        local_descriptors: descriptor("AAAAAAAAaaaa"),
+        ..node(
+            "node_a",
+            vec![
+                gossip(0, "Current database", DUMP),
+                gossip(1, "Sent Gossip", DUMP),
+                gossip(2, "Received Gossip", "digraph db {"),
+            ],
+        )
+    }])
+}
+
+#[test]
+fn test_export_formats() {
+    let nodes = network();
+    let registry = IdentityRegistry::build(&nodes);
+    let entry = &nodes["node_a"].gossip[0];
+    let export = |format| export_entry("node_a", 0, entry, format, &registry).unwrap();
+
+    assert_eq!(export(ExportFormat::Dot), DUMP);
+
+    let graphml = export(ExportFormat::GraphMl);
+    assert!(graphml.starts_with("<?xml"));
+    assert!(graphml.contains(r#"<node id="AAAAAAAAaaaa">"#));
+    assert!(graphml.contains(r#"<data key="label">node_a (AAAAAAAA)</data>"#));
+    assert!(graphml.contains(r#"<data key="version">2</data>"#));
+    assert!(graphml.contains(r#"<edge source="BBBBBBBBbbbb" target="CCCCCCCC&lt;&amp;&gt;"/>"#));
+    assert_eq!(graphml.matches("<edge ").count(), 3);
+
+    // The layout does not depend on anything but the snapshot
+    let svg = export(ExportFormat::Svg);
+    assert_eq!(svg, export(ExportFormat::Svg));
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.contains(">node_a (AAAAAAAA)</text>"));
+    assert!(svg.contains(">BBBBBBBB</text>"));
+    assert!(svg.contains("CCCCCCCC&lt;&amp;&gt;"));
+    assert_eq!(svg.matches("<circle").count(), 3);
+    assert_eq!(svg.matches("<line").count(), 3);
+
+    assert!(export_entry(
+        "node_a",
+        2,
+        &nodes["node_a"].gossip[2],
+        ExportFormat::Svg,
+        &registry
+    )
+    .is_err());
+    assert_eq!(
+        export_file_name("node_a", 0, entry, ExportFormat::GraphMl),
+        "node_a_gossip_0_current-database.graphml"
+    );
+    assert_eq!("SVG".parse::<ExportFormat>(), Ok(ExportFormat::Svg));
+    assert!("png".parse::<ExportFormat>().is_err());
+}
+
+#[test]
+fn test_entry_range_and_write_entries() {
+    let nodes = network();
+    let node = &nodes["node_a"];
+    assert_eq!(entry_range(node, None, None).unwrap(), 0..=2);
+    assert_eq!(entry_range(node, Some(1), None).unwrap(), 1..=2);
+    assert_eq!(entry_range(node, None, Some(0)).unwrap(), 0..=0);
+    assert!(entry_range(node, Some(2), Some(1)).is_err());
+    assert!(entry_range(node, None, Some(3)).is_err());
+    assert!(entry_range(&NodeData::default(), None, None).is_err());
+
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("exports");
+    let registry = IdentityRegistry::build(&nodes);
+    let written = write_entries(node, 0..=1, ExportFormat::Dot, &registry, &output).unwrap();
+    let names: Vec<String> = written
+        .iter()
+        .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "node_a_gossip_0_current-database.dot",
+            "node_a_gossip_1_sent-gossip.dot"
+        ]
+    );
+    assert_eq!(std::fs::read_to_string(&written[1]).unwrap(), DUMP);
+}
+
+#[tokio::test]
+async fn test_gossip_export_routes() {
+    let state = app_state(network(), &std::env::temp_dir());
+    let app = axum::Router::new()
+        .route(
+            "/api/gossip/:node_name/export",
+            get(get_gossip_export_range),
+        )
+        .route(
+            "/api/gossip/:node_name/:index/export",
+            get(get_gossip_export),
+        )
+        .with_state(state);
+
+    let get_uri = |uri: &str| {
+        app.clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+    };
+
+    let response = get_uri("/api/gossip/node_a/1/export?format=svg")
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "image/svg+xml; charset=utf-8"
+    );
+    assert_eq!(
+        response.headers()[header::CONTENT_DISPOSITION],
+        "attachment; filename=\"node_a_gossip_1_sent-gossip.svg\""
+    );
+
+    let response = get_uri("/api/gossip/node_a/export?format=graphml&to=1")
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let mut archive = zip::ZipArchive::new(Cursor::new(body.to_vec())).unwrap();
+    assert_eq!(archive.len(), 2);
+    let mut graphml = String::new();
+    archive
+        .by_name("node_a_gossip_1_sent-gossip.graphml")
+        .unwrap()
+        .read_to_string(&mut graphml)
+        .unwrap();
+    assert!(graphml.contains("node_a (AAAAAAAA)"));
+
+    for (uri, status) in [
+        (
+            "/api/gossip/node_a/2/export?format=svg",
+            StatusCode::UNPROCESSABLE_ENTITY,
+        ),
+        (
+            "/api/gossip/node_a/3/export?format=svg",
+            StatusCode::NOT_FOUND,
+        ),
+        (
+            "/api/gossip/node_a/0/export?format=png",
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            "/api/gossip/node_a/export?format=svg",
+            StatusCode::UNPROCESSABLE_ENTITY,
+        ),
+        (
+            "/api/gossip/node_a/export?format=dot&from=5",
+            StatusCode::NOT_FOUND,
+        ),
+        (
+            "/api/gossip/node_9/export?format=dot",
+            StatusCode::NOT_FOUND,
+        ),
+    ] {
+        assert_eq!(get_uri(uri).await.unwrap().status(), status, "{}", uri);
+    }
+}
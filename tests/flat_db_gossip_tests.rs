@@ -26,6 +26,9 @@ async fn test_flat_structure_database_api() {
         nodes_data: Arc::new(RwLock::new(nodes)),
         input_dir: test_dir,
         tera: Arc::new(tera),
+        log_index: Default::default(),
+        redactor: Default::default(),
+        identities: Default::default(),
     };
 
     let app = Router::new()
@@ -104,6 +107,9 @@ async fn test_flat_structure_gossip_parsing() {
         nodes_data: Arc::new(RwLock::new(nodes)),
         input_dir: test_dir,
         tera: Arc::new(tera),
+        log_index: Default::default(),
+        redactor: Default::default(),
+        identities: Default::default(),
     };
 
     let app = Router::new()
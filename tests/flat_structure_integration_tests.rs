@@ -23,6 +23,9 @@ fn setup_flat_test_app() -> Router {
         nodes_data: Arc::new(RwLock::new(nodes)),
         input_dir: test_dir,
         tera: Arc::new(tera::Tera::default()),
+        log_index: Default::default(),
+        redactor: Default::default(),
+        identities: Default::default(),
     };
 
     Router::new()
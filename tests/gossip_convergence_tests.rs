@@ -0,0 +1,136 @@
+use masq_log_visualizer::gossip::analyze_convergence;
+use masq_log_visualizer::identity::IdentityRegistry;
+use masq_log_visualizer::models::{AllNodesData, GossipEntry};
+
+mod common;
+use common::{gossip, node};
+
+/// A `Current database` dump knowing `keys`, each linked to the next.
+fn database(keys: &[&str]) -> String {
+    let mut statements: Vec<String> = keys
+        .iter()
+        .map(|key| format!("\"{}\" [label=\"AR v1 US\\n{}\"]", key, key))
+        .collect();
+    for pair in keys.windows(2) {
+        statements.push(format!("\"{}\" -> \"{}\"", pair[0], pair[1]));
+    }
+    format!("digraph db {{ {} }}", statements.join("; "))
+}
+
+/// A sent gossip that tells which public key a node has.
+fn introduce(key: &str) -> GossipEntry {
+    gossip(
+        59,
+        "Sent Gossip",
+        format!("digraph db {{ \"{}\" [style=filled] }}", key),
+    )
+}
+
+fn network() -> AllNodesData {
+    common::network([
+        node(
+            "node_a",
+            vec![
+                introduce("KA"),
+                gossip(0, "Current database", database(&["KA"])),
+                gossip(2, "Current database", database(&["KA", "KB"])),
+                gossip(5, "Current database", database(&["KA", "KB", "KC"])),
+                // Dumping the same view again changes nothing
+                gossip(7, "Current database", database(&["KA", "KB", "KC"])),
+            ],
+        ),
+        node(
+            "node_b",
+            vec![
+                introduce("KB"),
+                gossip(1, "Current database", database(&["KA", "KB"])),
+                gossip(6, "Current database", database(&["KA", "KB", "KC"])),
+            ],
+        ),
+        node(
+            "node_c",
+            vec![
+                introduce("KC"),
+                gossip(3, "Current database", database(&["KA", "KB"])),
+                gossip(4, "Current database", database(&["KA", "KB", "KC"])),
+            ],
+        ),
+    ])
+}
+
+#[test]
+fn test_analyze_convergence() {
+    let nodes = network();
+    let convergence = analyze_convergence(&nodes, &IdentityRegistry::build(&nodes));
+    assert_eq!(convergence.nodes, ["node_a", "node_b", "node_c"]);
+
+    let states: Vec<(&str, bool, usize)> = convergence
+        .snapshots
+        .iter()
+        .map(|s| (&s.timestamp[17..19], s.converged, s.groups.len()))
+        .collect();
+    assert_eq!(
+        states,
+        [
+            ("00", false, 1),
+            ("01", false, 2),
+            ("02", false, 1),
+            ("03", true, 1),
+            ("04", false, 2),
+            ("05", false, 2),
+            ("06", true, 1),
+        ]
+    );
+
+    // node_b knows node_a's peer that node_a does not know yet
+    let second = &convergence.snapshots[1];
+    assert_eq!(second.missing["node_a"], ["node_b"]);
+    assert!(!second.missing.contains_key("node_b"));
+    assert_eq!(
+        convergence.snapshots[5].groups,
+        [vec!["node_a", "node_c"], vec!["node_b"]]
+    );
+
+    let joins: Vec<(&str, &str, Option<i64>)> = convergence
+        .joins
+        .iter()
+        .map(|j| {
+            (
+                j.node.as_deref().unwrap(),
+                j.first_seen_by.as_str(),
+                j.convergence_ms,
+            )
+        })
+        .collect();
+    assert_eq!(
+        joins,
+        [
+            ("node_a", "node_a", Some(3000)),
+            ("node_b", "node_b", Some(2000)),
+            ("node_c", "node_c", Some(2000)),
+        ]
+    );
+
+    assert!(convergence.converged);
+    assert_eq!(
+        convergence.converged_since.as_deref(),
+        Some("2025-02-03 20:00:06.000")
+    );
+}
+
+#[test]
+fn test_convergence_never_reached() {
+    let mut nodes = network();
+    nodes.get_mut("node_b").unwrap().gossip.pop();
+
+    let convergence = analyze_convergence(&nodes, &IdentityRegistry::build(&nodes));
+    assert!(!convergence.converged);
+    assert_eq!(convergence.converged_since, None);
+    let last = convergence.snapshots.last().unwrap();
+    assert_eq!(last.missing["node_b"], ["node_c"]);
+    assert_eq!(convergence.joins[2].converged_at, None);
+    assert_eq!(
+        convergence.joins[0].converged_at.as_deref(),
+        Some("2025-02-03 20:00:03.000")
+    );
+}
@@ -0,0 +1,181 @@
+use masq_log_visualizer::dot::parse_gossip_graph;
+use masq_log_visualizer::gossip::{correlate_gossip, gossip_fingerprint, topology_fingerprint};
+use masq_log_visualizer::identity::IdentityRegistry;
+use masq_log_visualizer::logs::timestamp_millis;
+use masq_log_visualizer::models::AllNodesData;
+
+mod common;
+use common::{gossip_at, node};
+
+/// A gossip about `records` (key, version) from `source` to `target`.
+fn dump(records: &[(&str, u32)], source: &str, target: &str) -> String {
+    let mut statements: Vec<String> = records
+        .iter()
+        .map(|(key, version)| {
+            let style = if *key == source {
+                " [style=filled]"
+            } else if *key == target {
+                " [shape=box]"
+            } else {
+                ""
+            };
+            format!(
+                "\"{}\" [label=\"AR v{} US\\n{}\"]{}",
+                key, version, key, style
+            )
+        })
+        .collect();
+    for key in [source, target] {
+        if !records.iter().any(|(k, _)| *k == key) {
+            let style = if key == source {
+                "style=filled"
+            } else {
+                "shape=box"
+            };
+            statements.push(format!("\"{}\" [label=\"{}\"] [{}]", key, key, style));
+        }
+    }
+    format!("digraph db {{ {} }}", statements.join("; "))
+}
+
+fn network() -> AllNodesData {
+    common::network([
+        node(
+            "node_a",
+            vec![
+                gossip_at(
+                    "2025-02-03 20:00:00.000",
+                    "Sent Gossip",
+                    dump(&[("KA", 1)], "KA", "KB"),
+                ),
+                gossip_at(
+                    "2025-02-03 20:00:01.000",
+                    "Sent Gossip",
+                    dump(&[("KA", 1)], "KA", "KC"),
+                ),
+            ],
+        ),
+        node(
+            "node_b",
+            vec![
+                // Received logged before it is sent onwards, slightly out of order
+                gossip_at(
+                    "2025-02-03 20:00:00.300",
+                    "Sent Gossip",
+                    dump(&[("KA", 1), ("KB", 1)], "KB", "KC"),
+                ),
+                gossip_at(
+                    "2025-02-03 20:00:00.150",
+                    "Received Gossip",
+                    dump(&[("KA", 1)], "KA", "KB"),
+                ),
+            ],
+        ),
+        node(
+            "node_c",
+            vec![gossip_at(
+                "2025-02-03 20:00:00.700",
+                "Received Gossip",
+                dump(&[("KA", 1), ("KB", 1)], "KB", "KC"),
+            )],
+        ),
+    ])
+}
+
+#[test]
+fn test_timestamp_millis() {
+    assert_eq!(timestamp_millis("1970-01-01 00:00:00.000"), Some(0));
+    assert_eq!(
+        timestamp_millis("2025-02-03 20:00:01.250"),
+        Some(1_738_612_801_250)
+    );
+    assert_eq!(
+        timestamp_millis("2024-03-01T00:00:00"),
+        Some(1_709_251_200_000)
+    );
+    assert_eq!(timestamp_millis("2025-13-01 00:00:00.000"), None);
+    assert_eq!(timestamp_millis("garbage"), None);
+}
+
+#[test]
+fn test_fingerprint_ignores_sender_and_target_styling() {
+    let sent = parse_gossip_graph(&dump(&[("KA", 1)], "KA", "KB")).unwrap();
+    let plain = parse_gossip_graph(
+        "digraph db { \"KA\" [label=\"AR v1 US\\nKA\"]; \"KB\" [label=\"KB\"] }",
+    )
+    .unwrap();
+    let newer = parse_gossip_graph(&dump(&[("KA", 2)], "KA", "KB")).unwrap();
+    assert_eq!(gossip_fingerprint(&sent), gossip_fingerprint(&plain));
+    assert_ne!(gossip_fingerprint(&sent), gossip_fingerprint(&newer));
+}
+
+#[test]
+fn test_fingerprints_are_stable() {
+    // Pinned, so gossip matched across runs and toolchains keeps its identity
+    let graph = parse_gossip_graph(&dump(&[("KA", 1), ("KB", 3)], "KA", "KB")).unwrap();
+    assert_eq!(gossip_fingerprint(&graph), "170a6be29c4b31c0");
+    assert_eq!(topology_fingerprint(&graph), "f509acbe54091d02");
+}
+
+#[test]
+fn test_correlate_gossip() {
+    let nodes = network();
+    let correlation = correlate_gossip(&nodes, &IdentityRegistry::build(&nodes));
+
+    assert_eq!(correlation.node_keys["node_a"], "KA");
+    assert_eq!(correlation.node_keys["node_b"], "KB");
+    assert_eq!(correlation.node_keys["node_c"], "KC");
+
+    let hops: Vec<(&str, Option<&str>, Option<i64>)> = correlation
+        .hops
+        .iter()
+        .map(|h| (h.from_node.as_str(), h.to_node.as_deref(), h.delay_ms))
+        .collect();
+    assert_eq!(
+        hops,
+        [
+            ("node_a", Some("node_b"), Some(150)),
+            ("node_b", Some("node_c"), Some(400)),
+            // Never logged as received
+            ("node_a", Some("node_c"), None),
+        ]
+    );
+    assert_eq!(correlation.hops[0].received_index, Some(1));
+
+    let a = correlation
+        .propagations
+        .iter()
+        .find(|p| p.public_key == "KA")
+        .unwrap();
+    assert_eq!(a.subject_node.as_deref(), Some("node_a"));
+    assert_eq!(a.origin_node, "node_a");
+    let arrivals: Vec<(&str, i64, Option<&str>)> = a
+        .arrivals
+        .iter()
+        .map(|r| (r.node.as_str(), r.delay_ms, r.via.as_deref()))
+        .collect();
+    assert_eq!(
+        arrivals,
+        [
+            ("node_b", 150, Some("node_a")),
+            ("node_c", 700, Some("node_b"))
+        ]
+    );
+    assert!(a.missing.is_empty());
+
+    let b = correlation
+        .propagations
+        .iter()
+        .find(|p| p.public_key == "KB")
+        .unwrap();
+    assert_eq!(b.origin_at, "2025-02-03 20:00:00.300");
+    assert_eq!(b.arrivals.len(), 1);
+    assert_eq!(b.missing, ["node_a"]);
+}
+
+#[test]
+fn test_correlate_without_gossip() {
+    let correlation = correlate_gossip(&AllNodesData::new(), &IdentityRegistry::default());
+    assert!(correlation.hops.is_empty());
+    assert!(correlation.propagations.is_empty());
+}
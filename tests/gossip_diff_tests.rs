@@ -0,0 +1,128 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::routing::get;
+use masq_log_visualizer::dot::parse_gossip_graph;
+use masq_log_visualizer::models::{AllNodesData, GossipEntry, NodeData};
+use masq_log_visualizer::routes::{get_gossip_diff, get_gossip_graph, AppState};
+use std::sync::{Arc, RwLock};
+use tower::ServiceExt;
+
+const BEFORE: &str = r#"digraph db { "A" [label="AR v1 US\nA\n1.1.1.1:1000"] [style=filled]; "B" [label="AR v1 FR\nB\n2.2.2.2:2000"]; "C" [label="Ar v1 DE\nC"]; "A" -> "B"; "B" -> "A"; "B" -> "C" }"#;
+const AFTER: &str = r#"digraph db { "A" [label="AR v1 US\nA\n1.1.1.1:1000"] [style=filled]; "B" [label="AR v2 FR\nB\n2.2.2.2:2001"]; "D" [label="AR v1 NL\nD"]; "A" -> "B"; "B" -> "A"; "A" -> "D" }"#;
+
+#[test]
+fn test_gossip_graph_diff() {
+    let before = parse_gossip_graph(BEFORE).unwrap();
+    let after = parse_gossip_graph(AFTER).unwrap();
+    let diff = before.diff(&after);
+
+    let keys = |nodes: &[masq_log_visualizer::models::GossipNode]| {
+        nodes
+            .iter()
+            .map(|n| n.public_key.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(keys(&diff.added_nodes), ["D"]);
+    assert_eq!(keys(&diff.removed_nodes), ["C"]);
+    assert_eq!(diff.added_edges.len(), 1);
+    assert_eq!(
+        (
+            diff.added_edges[0].from.as_str(),
+            diff.added_edges[0].to.as_str()
+        ),
+        ("A", "D")
+    );
+    assert_eq!(diff.removed_edges.len(), 1);
+    assert_eq!(diff.removed_edges[0].to, "C");
+
+    assert_eq!(diff.changed_nodes.len(), 1);
+    let change = &diff.changed_nodes[0];
+    assert_eq!(change.public_key, "B");
+    let fields: Vec<(&str, Option<&str>, Option<&str>)> = change
+        .changes
+        .iter()
+        .map(|c| (c.field.as_str(), c.old.as_deref(), c.new.as_deref()))
+        .collect();
+    assert_eq!(
+        fields,
+        [
+            ("version", Some("1"), Some("2")),
+            ("ports", Some("2000"), Some("2001"))
+        ]
+    );
+
+    assert!(before.diff(&before).is_empty());
+}
+
+#[tokio::test]
+async fn test_gossip_diff_route() {
+    let entry = |dot: &str| GossipEntry {
+        timestamp: "2025-02-03 20:00:01.000".to_string(),
+        actor: "Neighborhood".to_string(),
+        tag: "Current database".to_string(),
+        dot: dot.to_string(),
+    };
+    let mut nodes = AllNodesData::new();
+    nodes.insert(
+        "node_1".to_string(),
+        NodeData {
+            name: "node_1".to_string(),
+            gossip: vec![entry(BEFORE), entry(AFTER), entry("digraph db {")],
+            ..Default::default()
+        },
+    );
+    let state = AppState {
+        nodes_data: Arc::new(RwLock::new(nodes)),
+        input_dir: std::env::temp_dir(),
+        tera: Arc::new(tera::Tera::default()),
+        log_index: Default::default(),
+        redactor: Default::default(),
+        identities: Default::default(),
+    };
+    let app = axum::Router::new()
+        .route("/api/gossip/:node_name/diff", get(get_gossip_diff))
+        .route("/api/gossip/:node_name/:index/graph", get(get_gossip_graph))
+        .with_state(state);
+
+    let get_uri = |uri: &str| {
+        app.clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+    };
+
+    let response = get_uri("/api/gossip/node_1/diff?from=0").await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["addedNodes"][0]["publicKey"], "D");
+    assert_eq!(json["removedNodes"][0]["publicKey"], "C");
+    assert_eq!(json["changedNodes"][0]["changes"][0]["field"], "version");
+
+    // Going backwards swaps additions and removals
+    let response = get_uri("/api/gossip/node_1/diff?from=1&to=0")
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["addedNodes"][0]["publicKey"], "C");
+
+    for (uri, status) in [
+        (
+            "/api/gossip/node_1/diff?from=1",
+            StatusCode::UNPROCESSABLE_ENTITY,
+        ),
+        ("/api/gossip/node_1/diff?from=2", StatusCode::NOT_FOUND),
+        ("/api/gossip/node_9/diff?from=0", StatusCode::NOT_FOUND),
+        ("/api/gossip/node_1/diff", StatusCode::BAD_REQUEST),
+        (
+            "/api/gossip/node_1/diff?from=18446744073709551615",
+            StatusCode::BAD_REQUEST,
+        ),
+        ("/api/gossip/node_1/0/graph", StatusCode::OK),
+    ] {
+        assert_eq!(get_uri(uri).await.unwrap().status(), status, "{}", uri);
+    }
+}
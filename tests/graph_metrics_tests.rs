@@ -0,0 +1,197 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::routing::get;
+use masq_log_visualizer::dot::parse_gossip_graph;
+use masq_log_visualizer::graph_metrics::{graph_metrics, metrics_series};
+use masq_log_visualizer::identity::IdentityRegistry;
+use masq_log_visualizer::models::{AllNodesData, GossipEntry, NodeData};
+use masq_log_visualizer::routes::{get_gossip_metrics, get_gossip_metrics_series};
+use std::collections::BTreeMap;
+use tower::ServiceExt;
+
+mod common;
+use common::{app_state, descriptor, gossip, node};
+
+/// A, B, C and D are full neighbors in a chain with a B-D shortcut; E only
+/// half-links to A and F is on its own. C does not route data.
+const NETWORK: &str = r#"digraph db { "AAAAAAAA" [label="AR v1 US\nAAAAAAAA"] [style=filled]; "BBBBBBBB" [label="AR v1 US\nBBBBBBBB"]; "CCCCCCCC" [label="Ar v1 US\nCCCCCCCC"]; "DDDDDDDD" [label="AR v1 US\nDDDDDDDD"]; "EEEEEEEE" [label="AR v1 US\nEEEEEEEE"]; "FFFFFFFF" [label="AR v1 US\nFFFFFFFF"]; "AAAAAAAA" -> "BBBBBBBB"; "BBBBBBBB" -> "AAAAAAAA"; "BBBBBBBB" -> "CCCCCCCC"; "CCCCCCCC" -> "BBBBBBBB"; "CCCCCCCC" -> "DDDDDDDD"; "DDDDDDDD" -> "CCCCCCCC"; "BBBBBBBB" -> "DDDDDDDD"; "DDDDDDDD" -> "BBBBBBBB"; "EEEEEEEE" -> "AAAAAAAA" }"#;
+
+fn entry(second: u32, dot: &str) -> GossipEntry {
+    gossip(second, "Current database", dot)
+}
+
+fn network() -> AllNodesData {
+    common::network([
+        NodeData {
+            local_descriptors: descriptor("AAAAAAAA"),
+            ..node(
+                "node_a",
+                vec![
+                    entry(0, r#"digraph db { "AAAAAAAA" [style=filled] }"#),
+                    entry(1, "digraph db {"),
+                    entry(2, NETWORK),
+                ],
+            )
+        },
+        NodeData {
+            local_descriptors: descriptor("EEEEEEEE"),
+            ..NodeData::new("node_e")
+        },
+    ])
+}
+
+#[test]
+fn test_graph_metrics() {
+    let registry = IdentityRegistry::build(&network());
+    let metrics = graph_metrics(&parse_gossip_graph(NETWORK).unwrap(), &registry);
+
+    assert_eq!(metrics.node_count, 6);
+    assert_eq!(metrics.edge_count, 9);
+    assert_eq!(
+        metrics.degree_distribution,
+        BTreeMap::from([(0, 1), (1, 1), (2, 3), (3, 1)])
+    );
+    assert_eq!(
+        metrics.components,
+        [
+            vec!["AAAAAAAA", "BBBBBBBB", "CCCCCCCC", "DDDDDDDD", "EEEEEEEE"],
+            vec!["FFFFFFFF"],
+        ]
+    );
+    // E to C or D, through A and B
+    assert_eq!(metrics.diameter, Some(3));
+    assert_eq!(metrics.articulation_points, ["AAAAAAAA", "BBBBBBBB"]);
+
+    let routes: Vec<(&str, Option<&Vec<String>>)> = metrics
+        .local_routes
+        .iter()
+        .map(|r| (r.node.as_str(), r.route.as_ref()))
+        .collect();
+    // C cannot relay, but can be the exit; E has no full neighbor
+    let via_d = vec![
+        "BBBBBBBB".to_string(),
+        "DDDDDDDD".to_string(),
+        "CCCCCCCC".to_string(),
+    ];
+    assert_eq!(routes, [("node_a", Some(&via_d)), ("node_e", None)]);
+    assert!(!metrics.all_local_routable);
+}
+
+/// A snapshot of `keys` with every pair in `links` full neighbors.
+fn snapshot(keys: &[&str], links: &[(&str, &str)]) -> String {
+    let mut statements: Vec<String> = keys
+        .iter()
+        .map(|key| format!("\"{}\" [label=\"AR v1 US\\n{}\"]", key, key))
+        .collect();
+    for (a, b) in links {
+        statements.push(format!("\"{}\" -> \"{}\"", a, b));
+        statements.push(format!("\"{}\" -> \"{}\"", b, a));
+    }
+    format!("digraph db {{ {} }}", statements.join("; "))
+}
+
+#[test]
+fn test_articulation_points() {
+    let registry = IdentityRegistry::build(&AllNodesData::new());
+    let points = |keys: &[&str], links: &[(&str, &str)]| {
+        let graph = parse_gossip_graph(&snapshot(keys, links)).unwrap();
+        graph_metrics(&graph, &registry).articulation_points
+    };
+
+    // A ring holds together without any one node
+    let ring = [("A", "B"), ("B", "C"), ("C", "D"), ("D", "A")];
+    assert!(points(&["A", "B", "C", "D"], &ring).is_empty());
+    // A tail hangs on the ring by C
+    let mut tailed = ring.to_vec();
+    tailed.extend([("C", "E"), ("E", "F")]);
+    assert_eq!(points(&["A", "B", "C", "D", "E", "F"], &tailed), ["C", "E"]);
+    // Two triangles sharing B, and the centre of a star
+    let bowtie = [
+        ("A", "B"),
+        ("B", "C"),
+        ("C", "A"),
+        ("B", "D"),
+        ("D", "E"),
+        ("E", "B"),
+    ];
+    assert_eq!(points(&["A", "B", "C", "D", "E"], &bowtie), ["B"]);
+    let star = [("S", "A"), ("S", "B"), ("S", "C")];
+    assert_eq!(points(&["A", "B", "C", "S"], &star), ["S"]);
+    // Lone nodes and pairs split nothing
+    assert!(points(&["A", "B", "C"], &[("A", "B")]).is_empty());
+}
+
+#[test]
+fn test_no_local_node_is_not_routable() {
+    let registry = IdentityRegistry::build(&network());
+    let links = [("B", "C"), ("C", "D"), ("D", "E")];
+    let graph = parse_gossip_graph(&snapshot(&["B", "C", "D", "E"], &links)).unwrap();
+    let metrics = graph_metrics(&graph, &registry);
+    assert!(metrics.local_routes.is_empty());
+    assert!(!metrics.all_local_routable);
+}
+
+#[test]
+fn test_metrics_series() {
+    let nodes = network();
+    let registry = IdentityRegistry::build(&nodes);
+    let series = metrics_series(&nodes["node_a"], &registry);
+
+    // The unreadable dump is skipped
+    let points: Vec<(usize, usize, Option<usize>)> = series
+        .iter()
+        .map(|p| (p.index, p.metrics.node_count, p.metrics.diameter))
+        .collect();
+    assert_eq!(points, [(0, 1, Some(0)), (2, 6, Some(3))]);
+    assert!(!series[0].metrics.all_local_routable);
+}
+
+#[tokio::test]
+async fn test_gossip_metrics_routes() {
+    let state = app_state(network(), &std::env::temp_dir());
+    let app = axum::Router::new()
+        .route(
+            "/api/gossip/:node_name/metrics",
+            get(get_gossip_metrics_series),
+        )
+        .route(
+            "/api/gossip/:node_name/:index/metrics",
+            get(get_gossip_metrics),
+        )
+        .with_state(state);
+
+    let get_uri = |uri: &str| {
+        app.clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+    };
+
+    let response = get_uri("/api/gossip/node_a/2/metrics").await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["nodeCount"], 6);
+    assert_eq!(json["degreeDistribution"]["2"], 3);
+    assert_eq!(json["localRoutes"][0]["node"], "node_a");
+
+    let response = get_uri("/api/gossip/node_a/metrics").await.unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json.as_array().unwrap().len(), 2);
+    assert_eq!(json[1]["metrics"]["diameter"], 3);
+
+    for (uri, status) in [
+        (
+            "/api/gossip/node_a/1/metrics",
+            StatusCode::UNPROCESSABLE_ENTITY,
+        ),
+        ("/api/gossip/node_a/3/metrics", StatusCode::NOT_FOUND),
+        ("/api/gossip/node_9/0/metrics", StatusCode::NOT_FOUND),
+        ("/api/gossip/node_9/metrics", StatusCode::NOT_FOUND),
+    ] {
+        assert_eq!(get_uri(uri).await.unwrap().status(), status, "{}", uri);
+    }
+}
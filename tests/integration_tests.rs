@@ -2,7 +2,9 @@ use axum::{
     body::Body,
     http::{Request, StatusCode},
 };
-use masq_log_visualizer::{models::AllNodesData, parser::scan_directory, routes::AppState};
+use masq_log_visualizer::{
+    identity::IdentityRegistry, models::AllNodesData, parser::scan_directory, routes::AppState,
+};
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 use tera::Tera;
@@ -44,6 +46,9 @@ async fn setup_test_app() -> Option<(axum::Router, AllNodesData)> {
         nodes_data: Arc::new(RwLock::new(nodes_data.clone())),
         input_dir: test_dir.clone(),
         tera: Arc::new(tera),
+        log_index: Default::default(),
+        redactor: Default::default(),
+        identities: Arc::new(RwLock::new(IdentityRegistry::build(&nodes_data))),
     };
 
     use axum::routing::get;
@@ -73,6 +78,40 @@ async fn setup_test_app() -> Option<(axum::Router, AllNodesData)> {
             "/api/gossip/:node_name",
             get(masq_log_visualizer::routes::get_gossip),
         )
+        .route(
+            "/api/gossip/:node_name/events",
+            get(masq_log_visualizer::routes::get_gossip_events),
+        )
+        .route(
+            "/api/routes/:node_name",
+            get(masq_log_visualizer::routes::get_routes),
+        )
+        .route(
+            "/api/config/:node_name",
+            get(masq_log_visualizer::routes::get_config),
+        )
+        .route(
+            "/api/network/propagation",
+            get(masq_log_visualizer::routes::get_gossip_propagation),
+        )
+        .route(
+            "/api/network/convergence",
+            get(masq_log_visualizer::routes::get_gossip_convergence),
+        )
+        .route(
+            "/api/identities",
+            get(masq_log_visualizer::routes::get_identities),
+        )
+        .route("/timeline", get(masq_log_visualizer::routes::timeline_view))
+        .route("/network", get(masq_log_visualizer::routes::network_view))
+        .route(
+            "/api/network/topology",
+            get(masq_log_visualizer::routes::get_network_topology),
+        )
+        .route(
+            "/api/timeline",
+            get(masq_log_visualizer::routes::get_timeline),
+        )
         .with_state(app_state);
 
     Some((app, nodes_data))
@@ -116,7 +155,7 @@ async fn test_node_view_route() {
     let response = app
         .oneshot(
             Request::builder()
-                .uri(&format!("/node/{}", node_name))
+                .uri(format!("/node/{}", node_name))
                 .body(Body::empty())
                 .unwrap(),
         )
@@ -144,7 +183,7 @@ async fn test_api_db_tables() {
         let response = app
             .oneshot(
                 Request::builder()
-                    .uri(&format!("/api/db/{}", name))
+                    .uri(format!("/api/db/{}", name))
                     .body(Body::empty())
                     .unwrap(),
             )
@@ -168,7 +207,7 @@ async fn test_api_gossip() {
     let response = app
         .oneshot(
             Request::builder()
-                .uri(&format!("/api/gossip/{}", node_name))
+                .uri(format!("/api/gossip/{}", node_name))
                 .body(Body::empty())
                 .unwrap(),
         )
@@ -178,6 +217,108 @@ async fn test_api_gossip() {
     assert_eq!(response.status(), StatusCode::OK);
 }
 
+#[tokio::test]
+async fn test_gossip_events_route() {
+    let setup = setup_test_app().await;
+    if setup.is_none() {
+        return;
+    }
+    let (app, nodes_data) = setup.unwrap();
+
+    let node_name = nodes_data.keys().next().unwrap();
+    for (uri, status) in [
+        (format!("/api/gossip/{}/events", node_name), StatusCode::OK),
+        (
+            "/api/gossip/nonexistent_node_12345/events".to_string(),
+            StatusCode::NOT_FOUND,
+        ),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(&uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), status, "{}", uri);
+    }
+}
+
+#[tokio::test]
+async fn test_network_topology_route() {
+    let Some((app, nodes_data)) = setup_test_app().await else {
+        return;
+    };
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/network/topology")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let nodes = json["nodes"].as_array().unwrap();
+    assert!(nodes.len() >= nodes_data.len());
+    assert!(nodes[0]["publicKey"].is_string());
+    assert!(json["edges"].is_array());
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/network")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_routes_route() {
+    let Some((app, nodes_data)) = setup_test_app().await else {
+        return;
+    };
+
+    let node_name = nodes_data.keys().next().unwrap();
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/routes/{}", node_name))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(json["routes"].is_array());
+    assert!(json["edges"].is_array());
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/routes/nonexistent_node_12345")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
 #[tokio::test]
 async fn test_nonexistent_node() {
     let setup = setup_test_app().await;
@@ -215,7 +356,7 @@ async fn test_log_range_api() {
             let response = app
                 .oneshot(
                     Request::builder()
-                        .uri(&format!(
+                        .uri(format!(
                             "/api/logs/{}/{}/range?fromEnd=true&lines=10",
                             node_name, log_file
                         ))
@@ -249,16 +390,14 @@ async fn test_api_db_table_not_found() {
         let response = app
             .oneshot(
                 Request::builder()
-                    .uri(&format!("/api/db/{}/nonexistent_table", name))
+                    .uri(format!("/api/db/{}/nonexistent_table", name))
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        // Should probably be 404 or 500 depending on implementation
-        // Let's check it's not 200 OK
-        assert_ne!(response.status(), StatusCode::OK);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
 }
 
@@ -275,7 +414,7 @@ async fn test_download_log_not_found() {
     let response = app
         .oneshot(
             Request::builder()
-                .uri(&format!("/api/logs/{}/nonexistent.log", node_name))
+                .uri(format!("/api/logs/{}/nonexistent.log", node_name))
                 .body(Body::empty())
                 .unwrap(),
         )
@@ -302,7 +441,7 @@ async fn test_log_range_invalid_params() {
             let response = app
                 .oneshot(
                     Request::builder()
-                        .uri(&format!(
+                        .uri(format!(
                             "/api/logs/{}/{}/range?lines=0",
                             node_name, log_file
                         ))
@@ -318,3 +457,219 @@ async fn test_log_range_invalid_params() {
         }
     }
 }
+
+#[tokio::test]
+async fn test_timeline_routes() {
+    let setup = setup_test_app().await;
+    if setup.is_none() {
+        return;
+    }
+    let (app, nodes_data) = setup.unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/timeline")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/timeline?lines=5000")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    use axum::body::to_bytes;
+    let body_bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+    assert_eq!(json["nodes"].as_array().unwrap().len(), nodes_data.len());
+
+    // Every node contributes, and the merge is in timestamp order
+    let records = json["records"].as_array().unwrap();
+    for node_name in nodes_data.keys() {
+        assert!(records.iter().any(|r| r["node"] == node_name.as_str()));
+    }
+    let timestamps: Vec<&str> = records
+        .iter()
+        .map(|r| r["timestamp"].as_str().unwrap())
+        .collect();
+    assert!(timestamps.windows(2).all(|w| w[0] <= w[1]));
+}
+
+#[tokio::test]
+async fn test_config_route() {
+    let setup = setup_test_app().await;
+    if setup.is_none() {
+        return;
+    }
+    let (app, _) = setup.unwrap();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/config/node_2")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    use axum::body::to_bytes;
+    let body_bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+    assert_eq!(json["config"]["neighborhoodMode"], "standard");
+    assert_eq!(json["configDel"]["neighborhoodMode"], "zero-hop");
+
+    // config.del drops ip and neighbors and switches to zero-hop
+    let diff_keys: Vec<&str> = json["diff"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| d["key"].as_str().unwrap())
+        .collect();
+    assert!(diff_keys.contains(&"ip"));
+    assert!(diff_keys.contains(&"neighborhood-mode"));
+    assert!(!diff_keys.contains(&"chain"));
+}
+
+#[tokio::test]
+async fn test_dashboard_config_matrix() {
+    let Some((app, _)) = setup_test_app().await else {
+        return;
+    };
+
+    let response = app
+        .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let html = String::from_utf8_lossy(&body);
+    assert!(html.contains("Configuration Comparison"));
+    assert!(html.contains("config-differs"));
+    assert!(html.contains("No neighbors in standard mode"));
+}
+
+#[tokio::test]
+async fn test_config_route_redacts_secrets() {
+    let Some((app, _)) = setup_test_app().await else {
+        return;
+    };
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/config/node_2")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["config"]["entries"]["db-password"], "********");
+    let url = json["config"]["entries"]["blockchain-service-url"]
+        .as_str()
+        .unwrap();
+    assert!(url.starts_with("https://polygon-amoy.g.alchemy.com/v2/"));
+    assert!(!url.contains("ZfoJ68"));
+}
+
+#[tokio::test]
+async fn test_gossip_propagation_route() {
+    let Some((app, nodes)) = setup_test_app().await else {
+        return;
+    };
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/network/propagation")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let sent = nodes
+        .values()
+        .flat_map(|n| &n.gossip)
+        .filter(|g| g.tag == "Sent Gossip")
+        .count();
+    assert_eq!(json["hops"].as_array().unwrap().len(), sent);
+    assert!(json["propagations"].is_array());
+}
+
+#[tokio::test]
+async fn test_gossip_convergence_route() {
+    let Some((app, _)) = setup_test_app().await else {
+        return;
+    };
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/network/convergence")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(json["snapshots"].is_array());
+    assert!(json["joins"].is_array());
+    assert!(json["converged"].is_boolean());
+}
+
+#[tokio::test]
+async fn test_identities_route() {
+    let Some((app, nodes_data)) = setup_test_app().await else {
+        return;
+    };
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/identities")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let identities = json["identities"].as_array().unwrap();
+    assert!(identities.len() >= nodes_data.len());
+    assert!(identities[0]["publicKey"].is_string());
+    assert!(json["unknown"].is_array());
+}
@@ -0,0 +1,82 @@
+use masq_log_visualizer::logs::{component_matches, LogFilter};
+use masq_log_visualizer::models::LogLevel;
+use masq_log_visualizer::parser::parse_log_records;
+
+const SAMPLE: &str = "\
+2025-02-03 20:00:46.347 Thd9: DEBUG: Neighborhood: Connecting to persistent database
+2025-02-03 20:00:46.351 Thd6: INFO: ProxyClient: Adding DNS server: 1.1.1.1
+2025-02-03 20:00:51.008 Thd13: TRACE: hyper::buffer: get_buf []
+2025-02-03 20:00:51.008 Thd13: TRACE: hyper::http::h1: Request.try_parse Complete(192)
+2025-02-03 20:00:51.010 Thd15: WARN: Configurator: Failed to change password
+2025-02-03 20:01:02.000 Thd11: ERROR: Accountant: Payment failed
+  caused by: insufficient gas";
+
+fn messages(filter: &LogFilter) -> Vec<String> {
+    parse_log_records(SAMPLE)
+        .into_iter()
+        .filter(|r| filter.matches(r))
+        .map(|r| r.message)
+        .collect()
+}
+
+#[test]
+fn test_component_matching_includes_children() {
+    assert!(component_matches("hyper::buffer", "hyper"));
+    assert!(component_matches("hyper", "hyper"));
+    assert!(!component_matches("hyperx", "hyper"));
+    assert!(!component_matches("Neighborhood", "hyper"));
+}
+
+#[test]
+fn test_empty_filter_matches_everything() {
+    let filter = LogFilter::default();
+    assert!(filter.is_empty());
+    assert_eq!(messages(&filter).len(), 6);
+}
+
+#[test]
+fn test_level_and_component_filters() {
+    let filter = LogFilter {
+        min_level: Some(LogLevel::Info),
+        ..Default::default()
+    };
+    assert_eq!(
+        messages(&filter),
+        vec![
+            "Adding DNS server: 1.1.1.1",
+            "Failed to change password",
+            "Payment failed"
+        ]
+    );
+
+    let filter = LogFilter {
+        components: vec!["Neighborhood".to_string(), "Accountant".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(messages(&filter).len(), 2);
+
+    let filter = LogFilter {
+        exclude_components: vec!["hyper".to_string()],
+        thread: Some("Thd13".to_string()),
+        ..Default::default()
+    };
+    assert!(messages(&filter).is_empty());
+}
+
+#[test]
+fn test_time_window_and_regex_filters() {
+    let filter = LogFilter {
+        since: Some("2025-02-03 20:00:51".to_string()),
+        until: Some("2025-02-03 20:00".to_string()),
+        ..Default::default()
+    };
+    // `until` is a prefix bound, so the whole 20:00 minute is included
+    assert_eq!(messages(&filter).len(), 3);
+
+    // The regex sees continuation lines
+    let filter = LogFilter {
+        pattern: Some(regex::Regex::new("insufficient gas").unwrap()),
+        ..Default::default()
+    };
+    assert_eq!(messages(&filter), vec!["Payment failed"]);
+}
@@ -0,0 +1,307 @@
+use masq_log_visualizer::log_index::LogIndexCache;
+use masq_log_visualizer::parser::{parse_log_records, scan_directory};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use tempfile::TempDir;
+
+fn write_sample_log(path: &std::path::Path, records: usize) {
+    let mut file = File::create(path).unwrap();
+    writeln!(file, "          _____ ______  banner").unwrap();
+    for i in 0..records {
+        writeln!(
+            file,
+            "2025-02-03 20:{:02}:{:02}.000 Thd9: DEBUG: Neighborhood: Record {}",
+            i / 60 % 60,
+            i % 60,
+            i
+        )
+        .unwrap();
+        if i % 7 == 0 {
+            writeln!(file, "  detail line for {}", i).unwrap();
+        }
+    }
+}
+
+#[test]
+fn test_index_range_reads_match_full_parse() {
+    let temp_dir = TempDir::new().unwrap();
+    let log_path = temp_dir.path().join("MASQNode_rCURRENT.log");
+    write_sample_log(&log_path, 500);
+
+    let cache = LogIndexCache::new(temp_dir.path().join("cache"));
+    let index = cache.get(&log_path).unwrap();
+    let expected = parse_log_records(&std::fs::read_to_string(&log_path).unwrap());
+
+    // Banner record plus 500 log records
+    assert_eq!(index.len(), 501);
+    assert_eq!(index.len(), expected.len());
+    assert_eq!(index.read_records(0, 3).unwrap(), expected[0..3]);
+    assert_eq!(index.read_records(250, 260).unwrap(), expected[250..260]);
+    assert_eq!(index.read_records(495, 1000).unwrap(), expected[495..]);
+    assert!(index.read_records(600, 700).unwrap().is_empty());
+}
+
+#[test]
+fn test_index_extends_when_log_grows_and_rebuilds_on_truncation() {
+    let temp_dir = TempDir::new().unwrap();
+    let log_path = temp_dir.path().join("MASQNode_rCURRENT.log");
+    write_sample_log(&log_path, 10);
+
+    let cache = LogIndexCache::new(temp_dir.path().join("cache"));
+    assert_eq!(cache.get(&log_path).unwrap().len(), 11);
+
+    // A half-written line is not indexed until its line break arrives
+    let mut file = OpenOptions::new().append(true).open(&log_path).unwrap();
+    write!(
+        file,
+        "2025-02-03 21:00:00.000 Thd9: INFO: Accountant: Appended"
+    )
+    .unwrap();
+    file.flush().unwrap();
+    assert_eq!(cache.get(&log_path).unwrap().len(), 11);
+
+    writeln!(file).unwrap();
+    file.flush().unwrap();
+    let index = cache.get(&log_path).unwrap();
+    assert_eq!(index.len(), 12);
+    let last = index.read_records(11, 12).unwrap();
+    assert_eq!(last[0].message, "Appended");
+    assert_eq!(last[0].line, index.line_count() as usize - 1);
+
+    // Rotation replaces the file with a shorter one
+    write_sample_log(&log_path, 3);
+    let index = cache.get(&log_path).unwrap();
+    assert_eq!(index.len(), 4);
+    assert_eq!(index.read_records(3, 4).unwrap()[0].message, "Record 2");
+}
+
+#[test]
+fn test_index_rebuilds_when_log_is_replaced_by_larger_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let log_path = temp_dir.path().join("MASQNode_rCURRENT.log");
+    write_sample_log(&log_path, 10);
+
+    let cache = LogIndexCache::new(temp_dir.path().join("cache"));
+    assert_eq!(cache.get(&log_path).unwrap().len(), 11);
+
+    // Not an append: the indexed bytes changed, so the old offsets are void
+    let mut file = File::create(&log_path).unwrap();
+    for i in 0..30 {
+        writeln!(
+            file,
+            "2025-02-04 08:00:{:02}.000 Thd3: INFO: Accountant: Replacement {}",
+            i, i
+        )
+        .unwrap();
+    }
+    drop(file);
+
+    let index = cache.get(&log_path).unwrap();
+    let expected = parse_log_records(&std::fs::read_to_string(&log_path).unwrap());
+    assert_eq!(index.len(), 30);
+    for (i, record) in expected.iter().enumerate() {
+        assert_eq!(index.read_records(i, i + 1).unwrap()[0], *record);
+    }
+}
+
+#[test]
+fn test_concurrent_requests_share_one_index() {
+    let input_dir = TempDir::new().unwrap();
+    let cache_dir = TempDir::new().unwrap();
+    let log_path = input_dir.path().join("MASQNode_rCURRENT.log");
+    write_sample_log(&log_path, 2000);
+
+    let cache = LogIndexCache::new(cache_dir.path().to_path_buf());
+    let indexes: Vec<_> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..8)
+            .map(|_| scope.spawn(|| cache.get(&log_path).unwrap()))
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    // Only the first request built the index, the others got that one
+    for index in &indexes {
+        assert!(std::sync::Arc::ptr_eq(index, &indexes[0]));
+    }
+
+    let cached_files: Vec<_> = std::fs::read_dir(cache_dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(cached_files.len(), 1, "{:?}", cached_files);
+}
+
+#[test]
+fn test_index_is_persisted_outside_input_dir() {
+    let input_dir = TempDir::new().unwrap();
+    let cache_dir = TempDir::new().unwrap();
+    let log_path = input_dir.path().join("MASQNode_rCURRENT.log");
+    write_sample_log(&log_path, 50);
+
+    let index = LogIndexCache::new(cache_dir.path().to_path_buf())
+        .get(&log_path)
+        .unwrap();
+
+    let cached_files: Vec<_> = std::fs::read_dir(cache_dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    assert!(cached_files.iter().any(|name| name.ends_with(".idx")));
+    assert_eq!(std::fs::read_dir(input_dir.path()).unwrap().count(), 1);
+
+    // A fresh cache (e.g. after a restart) picks up the persisted index
+    let reloaded = LogIndexCache::new(cache_dir.path().to_path_buf())
+        .get(&log_path)
+        .unwrap();
+    assert_eq!(reloaded.len(), index.len());
+    assert_eq!(
+        reloaded.read_records(20, 25).unwrap(),
+        index.read_records(20, 25).unwrap()
+    );
+}
+
+#[test]
+fn test_corrupt_index_file_is_rebuilt() {
+    let input_dir = TempDir::new().unwrap();
+    let cache_dir = TempDir::new().unwrap();
+    let log_path = input_dir.path().join("MASQNode_rCURRENT.log");
+    write_sample_log(&log_path, 50);
+    let expected = LogIndexCache::new(cache_dir.path().to_path_buf())
+        .get(&log_path)
+        .unwrap();
+    let index_path = std::fs::read_dir(cache_dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .find(|p| p.extension().is_some_and(|e| e == "idx"))
+        .unwrap();
+    let intact = std::fs::read(&index_path).unwrap();
+
+    // Record count and path length follow the magic, a flag and five values
+    let count_at = 8 + 1 + 5 * 8;
+    let mut huge_count = intact.clone();
+    huge_count[count_at..count_at + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+    let mut huge_path = intact.clone();
+    huge_path[count_at + 8..count_at + 16].copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+    let truncated = intact[..intact.len() - 5].to_vec();
+
+    for damaged in [huge_count, huge_path, truncated] {
+        std::fs::write(&index_path, damaged).unwrap();
+        let index = LogIndexCache::new(cache_dir.path().to_path_buf())
+            .get(&log_path)
+            .unwrap();
+        assert_eq!(index.len(), expected.len());
+        assert_eq!(
+            index.read_records(40, 45).unwrap(),
+            expected.read_records(40, 45).unwrap()
+        );
+        // The rebuilt index replaced the damaged file
+        assert_eq!(std::fs::read(&index_path).unwrap(), intact);
+    }
+}
+
+#[test]
+fn test_index_over_gzip_archive() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    let input_dir = TempDir::new().unwrap();
+    let cache_dir = TempDir::new().unwrap();
+    let plain_path = input_dir.path().join("plain.log");
+    write_sample_log(&plain_path, 100);
+    let text = std::fs::read(&plain_path).unwrap();
+    std::fs::remove_file(&plain_path).unwrap();
+
+    let archive_path = input_dir.path().join("MASQNode_r00000.log.zip");
+    let mut encoder = GzEncoder::new(File::create(&archive_path).unwrap(), Compression::default());
+    encoder.write_all(&text).unwrap();
+    encoder.finish().unwrap();
+
+    let index = LogIndexCache::new(cache_dir.path().to_path_buf())
+        .get(&archive_path)
+        .unwrap();
+    assert_eq!(index.len(), 101);
+    assert_eq!(
+        index.read_records(100, 101).unwrap()[0].message,
+        "Record 99"
+    );
+
+    // The decompressed copy lives in the cache dir, not next to the archive
+    assert_eq!(std::fs::read_dir(input_dir.path()).unwrap().count(), 1);
+}
+
+fn write_gzip_log(path: &std::path::Path) {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    let mut encoder = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+    encoder
+        .write_all(b"2025-02-03 20:00:00.000 Thd9: DEBUG: Neighborhood: Record 0\n")
+        .unwrap();
+    encoder.finish().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cache_dir_is_private_to_its_owner() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    let archive_path = temp_dir.path().join("MASQNode_r00000.log.zip");
+    write_gzip_log(&archive_path);
+    let mode =
+        |path: &std::path::Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+    // Created for the owner only, and tightened if it already existed
+    let created = temp_dir.path().join("created");
+    LogIndexCache::new(created.clone())
+        .get(&archive_path)
+        .unwrap();
+    assert_eq!(mode(&created), 0o700);
+    let shared = temp_dir.path().join("shared");
+    std::fs::create_dir(&shared).unwrap();
+    std::fs::set_permissions(&shared, std::fs::Permissions::from_mode(0o777)).unwrap();
+    LogIndexCache::new(shared.clone())
+        .get(&archive_path)
+        .unwrap();
+    assert_eq!(mode(&shared), 0o700);
+
+    // A link planted where the cache dir should be is not followed
+    let target = temp_dir.path().join("target");
+    std::fs::create_dir(&target).unwrap();
+    let link = temp_dir.path().join("link");
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+    assert!(LogIndexCache::new(link).get(&archive_path).is_err());
+    assert_eq!(std::fs::read_dir(&target).unwrap().count(), 0);
+
+    // Nor is a directory of another user, where that can be set up
+    let foreign = temp_dir.path().join("foreign");
+    std::fs::create_dir(&foreign).unwrap();
+    std::fs::set_permissions(&foreign, std::fs::Permissions::from_mode(0o700)).unwrap();
+    if std::os::unix::fs::chown(&foreign, Some(65534), None).is_ok() {
+        assert!(LogIndexCache::new(foreign.clone())
+            .get(&archive_path)
+            .is_err());
+        assert_eq!(std::fs::read_dir(&foreign).unwrap().count(), 0);
+    }
+
+    // Plain logs are still indexed, only not persisted
+    let plain_path = temp_dir.path().join("plain.log");
+    write_sample_log(&plain_path, 10);
+    let cache = LogIndexCache::new(temp_dir.path().join("link"));
+    assert_eq!(cache.get(&plain_path).unwrap().len(), 11);
+    assert_eq!(std::fs::read_dir(&target).unwrap().count(), 0);
+}
+
+#[test]
+fn test_scan_keeps_tail_of_current_log() {
+    let temp_dir = TempDir::new().unwrap();
+    let node_dir = temp_dir.path().join("node_tail");
+    std::fs::create_dir(&node_dir).unwrap();
+    write_sample_log(&node_dir.join("MASQNode_rCURRENT.log"), 3000);
+
+    let nodes = scan_directory(temp_dir.path()).unwrap();
+    let current_log = &nodes["node_tail"].current_log;
+    let lines: Vec<&str> = current_log.lines().collect();
+
+    assert_eq!(lines.len(), 1000);
+    assert!(lines[999].ends_with("Record 2999"));
+}
@@ -0,0 +1,188 @@
+use masq_log_visualizer::database::{query_table, TableQuery};
+use masq_log_visualizer::logs::{format_unix_seconds, timestamp_millis};
+use masq_log_visualizer::masq_schema::{
+    format_masq, hexdump, join_wei, table_layout, ColumnKind, TableLayout,
+};
+use masq_log_visualizer::redact::Redactor;
+use rusqlite::types::ValueRef;
+use rusqlite::Connection;
+use std::path::Path;
+
+fn columns(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+/// A node database of `schema_version` with one payable of 10 MASQ and one
+/// negative receivable
+fn create_node_database(path: &Path, schema_version: &str) {
+    let conn = Connection::open(path).unwrap();
+    conn.execute_batch(&format!(
+        "CREATE TABLE config (name TEXT, value TEXT, encrypted INTEGER);
+         INSERT INTO config VALUES ('schema_version', '{}', 0), ('seed', x'0102', 1);
+         CREATE TABLE payable (wallet_address TEXT, balance_high_b INTEGER, balance_low_b INTEGER,
+             last_paid_timestamp INTEGER, pending_payable_rowid INTEGER);
+         INSERT INTO payable VALUES ('0xABCDEF', 1, 776627963145224192, 1700000000, NULL);
+         CREATE TABLE receivable (wallet_address TEXT, balance_high_b INTEGER, balance_low_b INTEGER,
+             last_received_timestamp INTEGER);
+         INSERT INTO receivable VALUES ('0x0123', -1, 9223372036354775808, 0);
+         CREATE TABLE blobs (data BLOB);
+         INSERT INTO blobs VALUES (x'00414243');",
+        schema_version
+    ))
+    .unwrap();
+}
+
+#[test]
+fn test_amounts_and_dates() {
+    assert_eq!(join_wei(1, 776627963145224192), 10_000_000_000_000_000_000);
+    assert_eq!(join_wei(-1, 9_223_372_036_354_775_808), -500_000_000);
+    assert_eq!(format_masq(10_000_000_000_000_000_000), "10 MASQ");
+    assert_eq!(format_masq(1_500_000_000_000_000_000), "1.5 MASQ");
+    assert_eq!(format_masq(-1), "-0.000000000000000001 MASQ");
+    assert_eq!(format_masq(0), "0 MASQ");
+
+    assert_eq!(format_unix_seconds(0), "1970-01-01 00:00:00");
+    assert_eq!(format_unix_seconds(1700000000), "2023-11-14 22:13:20");
+    assert_eq!(format_unix_seconds(-1), "1969-12-31 23:59:59");
+    for timestamp in [
+        "2024-02-29 12:34:56",
+        "2000-03-01 00:00:00",
+        "1999-12-31 23:59:59",
+    ] {
+        let seconds = timestamp_millis(timestamp).unwrap() / 1000;
+        assert_eq!(format_unix_seconds(seconds), timestamp);
+    }
+}
+
+#[test]
+fn test_hexdump() {
+    assert_eq!(hexdump(&[]), "0 bytes");
+    assert_eq!(
+        hexdump(b"\x00ABC"),
+        "4 bytes\n00000000  00 41 42 43                                      |.ABC|"
+    );
+    let dump = hexdump(&[0x20; 17]);
+    assert_eq!(dump.lines().count(), 3);
+    assert!(dump.contains("\n00000010  20 "));
+    assert!(hexdump(&[0; 5000]).ends_with("\n..."));
+}
+
+#[test]
+fn test_table_layout() {
+    let payable = columns(&[
+        "wallet_address",
+        "balance_high_b",
+        "balance_low_b",
+        "last_paid_timestamp",
+        "pending_payable_rowid",
+    ]);
+    let layout = table_layout(Some(9), "payable", &payable).unwrap();
+    assert_eq!(
+        layout.kinds,
+        [
+            Some(ColumnKind::Wallet),
+            Some(ColumnKind::WeiHigh),
+            Some(ColumnKind::WeiLow),
+            Some(ColumnKind::Timestamp),
+            None
+        ]
+    );
+    let row = [
+        ValueRef::Text(b"0xABCDEF"),
+        ValueRef::Integer(0),
+        ValueRef::Integer(1_500_000_000_000_000_000),
+        ValueRef::Integer(1700000000),
+        ValueRef::Null,
+    ];
+    assert_eq!(
+        layout.render_row(&row),
+        [
+            Some("0xabcdef".to_string()),
+            Some("1.5 MASQ".to_string()),
+            Some("1500000000000000000 wei".to_string()),
+            Some("2023-11-14 22:13:20 UTC".to_string()),
+            None
+        ]
+    );
+
+    // Unknown versions and unexpected columns fall back to generic rendering
+    assert_eq!(table_layout(None, "payable", &payable), None);
+    assert_eq!(table_layout(Some(3), "payable", &payable), None);
+    assert_eq!(table_layout(Some(99), "payable", &payable), None);
+    assert_eq!(
+        table_layout(Some(9), "payable", &columns(&["wallet_address", "balance"])),
+        None
+    );
+    assert_eq!(
+        table_layout(Some(9), "blobs", &columns(&["data"])),
+        Some(TableLayout { kinds: vec![None] })
+    );
+}
+
+#[test]
+fn test_typed_table_pages() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("node-data.db");
+    create_node_database(&db_path, "9");
+    let page = |table: &str| {
+        query_table(
+            &db_path,
+            table,
+            &TableQuery::default(),
+            &Redactor::default(),
+        )
+        .unwrap()
+    };
+
+    let payable = page("payable");
+    assert_eq!(payable.schema_version, Some(9));
+    assert_eq!(
+        payable.kinds.as_ref().unwrap()[1],
+        Some(ColumnKind::WeiHigh)
+    );
+    let display = &payable.display.as_ref().unwrap()[0];
+    assert_eq!(display[1].as_deref(), Some("10 MASQ"));
+    assert_eq!(display[2].as_deref(), Some("10000000000000000000 wei"));
+
+    let receivable = page("receivable");
+    let display = &receivable.display.as_ref().unwrap()[0];
+    assert_eq!(display[1].as_deref(), Some("-0.0000000005 MASQ"));
+    assert_eq!(display[3].as_deref(), Some("1970-01-01 00:00:00 UTC"));
+
+    let blobs = page("blobs");
+    assert_eq!(blobs.table.rows[0][0], "<BLOB 4 bytes>");
+    assert!(blobs.display.as_ref().unwrap()[0][0]
+        .as_deref()
+        .unwrap()
+        .starts_with("4 bytes\n00000000  00 41 42 43"));
+
+    // Secrets stay masked in their rendering too
+    let mut config = page("config");
+    config.redact(&Redactor::default());
+    let seed = config
+        .table
+        .rows
+        .iter()
+        .position(|row| row[0] == "seed")
+        .unwrap();
+    assert_eq!(config.display.as_ref().unwrap()[seed][1], None);
+
+    let json = serde_json::to_value(page("payable")).unwrap();
+    assert_eq!(json["kinds"][3], "timestamp");
+    assert_eq!(json["display"][0][0], "0xabcdef");
+
+    // Unknown schemas are rendered as before
+    create_node_database(&dir.path().join("old.db"), "3");
+    let old = query_table(
+        &dir.path().join("old.db"),
+        "payable",
+        &TableQuery::default(),
+        &Redactor::default(),
+    )
+    .unwrap();
+    assert_eq!(old.schema_version, Some(3));
+    assert_eq!(old.kinds, None);
+    assert_eq!(old.display, None);
+    let json = serde_json::to_value(old).unwrap();
+    assert!(json.get("kinds").is_none());
+}
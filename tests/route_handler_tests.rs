@@ -24,6 +24,9 @@ async fn test_download_log_handler() {
         nodes_data: Arc::new(RwLock::new(AllNodesData::new())),
         input_dir: temp_dir.path().to_path_buf(),
         tera: Arc::new(tera::Tera::default()),
+        log_index: Default::default(),
+        redactor: Default::default(),
+        identities: Default::default(),
     };
 
     let response = download_log(
@@ -70,6 +73,9 @@ async fn test_get_gossip_handler() {
         nodes_data: Arc::new(RwLock::new(nodes_map)),
         input_dir: temp_dir.path().to_path_buf(),
         tera: Arc::new(tera::Tera::default()),
+        log_index: Default::default(),
+        redactor: Default::default(),
+        identities: Default::default(),
     };
 
     let response = get_gossip(State(app_state), Path("node2".to_string()))
@@ -88,7 +94,6 @@ async fn test_get_db_tables_handler() {
     use masq_log_visualizer::routes::{get_db_tables, AppState};
     use rusqlite::{params, Connection};
     use std::fs::File;
-    use std::io::Write;
     use std::sync::{Arc, RwLock};
     use tempfile::TempDir;
 
@@ -121,6 +126,9 @@ async fn test_get_db_tables_handler() {
         nodes_data: Arc::new(RwLock::new(nodes_map)),
         input_dir: temp_dir.path().to_path_buf(),
         tera: Arc::new(tera::Tera::default()),
+        log_index: Default::default(),
+        redactor: Default::default(),
+        identities: Default::default(),
     };
 
     let response = get_db_tables(State(app_state), Path("node_db".to_string()))
@@ -141,9 +149,9 @@ async fn test_get_log_range_start_param() {
     use axum::http::StatusCode;
     use axum::response::IntoResponse;
     use masq_log_visualizer::models::AllNodesData;
-    use masq_log_visualizer::routes::{AppState, LogRangeParams};
+    use masq_log_visualizer::routes::{get_log_range, AppState, LogFilterParams, LogRangeParams};
     use std::fs::File;
-    use std::io::{BufRead, BufReader, Write};
+    use std::io::Write;
     use std::sync::{Arc, RwLock};
     use tempfile::TempDir;
     use tera::Tera;
@@ -155,13 +163,21 @@ async fn test_get_log_range_start_param() {
     let log_path = node_dir.join("sample.log");
     let mut file = File::create(&log_path).unwrap();
     for i in 1..=20 {
-        writeln!(file, "Line {}", i).unwrap();
+        writeln!(
+            file,
+            "2025-02-03 20:00:{:02}.000 Thd1: INFO: Test: Line {}",
+            i, i
+        )
+        .unwrap();
     }
 
     let app_state = AppState {
         nodes_data: Arc::new(RwLock::new(AllNodesData::new())),
         input_dir: temp_dir.path().to_path_buf(),
         tera: Arc::new(Tera::default()),
+        log_index: Default::default(),
+        redactor: Default::default(),
+        identities: Default::default(),
     };
 
     let params = LogRangeParams {
@@ -170,21 +186,127 @@ async fn test_get_log_range_start_param() {
         from_end: Some("false".to_string()),
     };
 
-    // Directly invoke the logic (replicating handler) to avoid full HTTP request
-    let reader = BufReader::new(File::open(&log_path).unwrap());
-    let all_lines: Vec<String> = reader.lines().map_while(Result::ok).collect();
-    let total_lines = all_lines.len();
-    let start = params.start.unwrap_or(0);
-    let end = (start + params.lines.unwrap_or(1000)).min(total_lines);
-    let lines = if start < total_lines {
-        all_lines[start..end].to_vec()
-    } else {
-        Vec::new()
-    };
+    let response = get_log_range(
+        State(app_state),
+        Path(("node_log".to_string(), "sample.log".to_string())),
+        Query(params),
+        Query(LogFilterParams::default()),
+    )
+    .await
+    .into_response();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body_bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
 
-    assert_eq!(total_lines, 20);
+    assert_eq!(body["totalRecords"], 20);
+    let messages: Vec<&str> = body["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["message"].as_str().unwrap())
+        .collect();
     assert_eq!(
-        lines,
+        messages,
         vec!["Line 6", "Line 7", "Line 8", "Line 9", "Line 10"]
     );
 }
+
+#[tokio::test]
+async fn test_get_log_range_server_side_filters() {
+    use axum::extract::{Path, Query, State};
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+    use masq_log_visualizer::models::AllNodesData;
+    use masq_log_visualizer::routes::{get_log_range, AppState, LogFilterParams, LogRangeParams};
+    use std::fs::File;
+    use std::io::Write;
+    use std::sync::{Arc, RwLock};
+    use tempfile::TempDir;
+    use tera::Tera;
+
+    let temp_dir = TempDir::new().unwrap();
+    let node_dir = temp_dir.path().join("node_filter");
+    std::fs::create_dir(&node_dir).unwrap();
+    let mut file = File::create(node_dir.join("sample.log")).unwrap();
+    for i in 0..50 {
+        writeln!(
+            file,
+            "2025-02-03 20:00:{:02}.000 Thd13: TRACE: hyper::buffer: get_buf []",
+            i
+        )
+        .unwrap();
+        if i % 5 == 0 {
+            writeln!(
+                file,
+                "2025-02-03 20:00:{:02}.500 Thd9: DEBUG: Neighborhood: Route {}",
+                i, i
+            )
+            .unwrap();
+        }
+    }
+
+    let app_state = AppState {
+        nodes_data: Arc::new(RwLock::new(AllNodesData::new())),
+        input_dir: temp_dir.path().to_path_buf(),
+        tera: Arc::new(Tera::default()),
+        log_index: Default::default(),
+        redactor: Default::default(),
+        identities: Default::default(),
+    };
+
+    let response = get_log_range(
+        State(app_state.clone()),
+        Path(("node_filter".to_string(), "sample.log".to_string())),
+        Query(LogRangeParams {
+            start: Some(1),
+            lines: Some(3),
+            from_end: None,
+        }),
+        Query(LogFilterParams {
+            level: Some("debug".to_string()),
+            exclude: Some("hyper".to_string()),
+            since: Some("2025-02-03T20:00:10".to_string()),
+            ..Default::default()
+        }),
+    )
+    .await
+    .into_response();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body_bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+
+    // Routes at seconds 10, 15, ..., 45 match; pagination applies to the matches
+    assert_eq!(body["totalUnfiltered"], 60);
+    assert_eq!(body["totalRecords"], 8);
+    let messages: Vec<&str> = body["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["message"].as_str().unwrap())
+        .collect();
+    assert_eq!(messages, vec!["Route 15", "Route 20", "Route 25"]);
+
+    // An invalid regex is rejected rather than silently ignored
+    let response = get_log_range(
+        State(app_state),
+        Path(("node_filter".to_string(), "sample.log".to_string())),
+        Query(LogRangeParams {
+            start: None,
+            lines: None,
+            from_end: None,
+        }),
+        Query(LogFilterParams {
+            regex: Some("(unclosed".to_string()),
+            ..Default::default()
+        }),
+    )
+    .await
+    .into_response();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
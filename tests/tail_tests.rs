@@ -0,0 +1,252 @@
+use masq_log_visualizer::models::LogRecord;
+use masq_log_visualizer::tail::{LogTail, TailEvent, TailReset, MAX_POLL_BYTES};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn append(path: &Path, text: &str) {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .unwrap();
+    file.write_all(text.as_bytes()).unwrap();
+    file.flush().unwrap();
+}
+
+fn records(events: &[TailEvent]) -> Vec<LogRecord> {
+    events
+        .iter()
+        .filter_map(|e| match e {
+            TailEvent::Records(records) => Some(records.clone()),
+            TailEvent::Reset(_) => None,
+        })
+        .flatten()
+        .collect()
+}
+
+fn messages(events: &[TailEvent]) -> Vec<String> {
+    records(events).into_iter().map(|r| r.message).collect()
+}
+
+#[test]
+fn test_tail_emits_appended_records() {
+    let temp_dir = TempDir::new().unwrap();
+    let log_path = temp_dir.path().join("MASQNode_rCURRENT.log");
+    append(
+        &log_path,
+        "2025-02-03 20:00:00.000 Thd1: INFO: Bootstrapper: old\n",
+    );
+    let start = std::fs::metadata(&log_path).unwrap().len();
+
+    let mut tail = LogTail::new(&log_path, start, 1).unwrap();
+    assert!(tail.poll().unwrap().is_empty());
+
+    append(
+        &log_path,
+        "2025-02-03 20:00:01.000 Thd1: INFO: Accountant: first\n\
+         2025-02-03 20:00:02.000 Thd1: DEBUG: Neighborhood: second\n  detail\n\
+         2025-02-03 20:00:03.000 Thd1: INFO: Accountant: thi",
+    );
+    // The last complete record is held back until its continuation lines are known
+    let events = tail.poll().unwrap();
+    assert_eq!(messages(&events), ["first"]);
+    assert_eq!(records(&events)[0].line, 1);
+
+    // Nothing new: the held-back record is released
+    let events = tail.poll().unwrap();
+    let second = records(&events);
+    assert_eq!(second.len(), 1);
+    assert_eq!(second[0].message, "second");
+    assert_eq!(second[0].line, 2);
+    assert_eq!(second[0].continuation, ["  detail"]);
+
+    // The half-written line is completed by the next write
+    append(&log_path, "rd\n");
+    tail.poll().unwrap();
+    let events = tail.poll().unwrap();
+    assert_eq!(messages(&events), ["third"]);
+    assert_eq!(records(&events)[0].line, 4);
+}
+
+#[test]
+fn test_tail_restarts_after_truncation() {
+    let temp_dir = TempDir::new().unwrap();
+    let log_path = temp_dir.path().join("MASQNode_rCURRENT.log");
+    append(
+        &log_path,
+        "2025-02-03 20:00:00.000 Thd1: INFO: Bootstrapper: a\n\
+         2025-02-03 20:00:01.000 Thd1: INFO: Bootstrapper: b\n",
+    );
+    let mut tail = LogTail::new(&log_path, 0, 0).unwrap();
+    tail.poll().unwrap();
+    tail.poll().unwrap();
+
+    File::create(&log_path).unwrap();
+    append(
+        &log_path,
+        "2025-02-03 21:00:00.000 Thd1: INFO: Bootstrapper: new\n",
+    );
+    let events = tail.poll().unwrap();
+    assert_eq!(events, [TailEvent::Reset(TailReset::Truncated)]);
+    let events = tail.poll().unwrap();
+    assert_eq!(messages(&events), ["new"]);
+    assert_eq!(records(&events)[0].line, 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_tail_follows_rotation() {
+    let temp_dir = TempDir::new().unwrap();
+    let log_path = temp_dir.path().join("MASQNode_rCURRENT.log");
+    append(
+        &log_path,
+        "2025-02-03 20:00:00.000 Thd1: INFO: Bootstrapper: a\n",
+    );
+    let mut tail = LogTail::new(&log_path, 0, 0).unwrap();
+    tail.poll().unwrap();
+    tail.poll().unwrap();
+
+    // A last write to the old file, then rotation and a fresh current log
+    append(
+        &log_path,
+        "2025-02-03 20:00:01.000 Thd1: INFO: Bootstrapper: last before rotation\n",
+    );
+    std::fs::rename(&log_path, temp_dir.path().join("MASQNode_r00000.log")).unwrap();
+    append(
+        &log_path,
+        "2025-02-03 20:00:02.000 Thd1: INFO: Bootstrapper: first after rotation\n\
+         2025-02-03 20:00:03.000 Thd1: INFO: Bootstrapper: second after rotation\n",
+    );
+
+    let events = tail.poll().unwrap();
+    assert_eq!(
+        events[0],
+        TailEvent::Records(vec![LogRecord {
+            line: 1,
+            timestamp: Some("2025-02-03 20:00:01.000".to_string()),
+            thread: Some("Thd1".to_string()),
+            level: Some(masq_log_visualizer::models::LogLevel::Info),
+            component: Some("Bootstrapper".to_string()),
+            message: "last before rotation".to_string(),
+            continuation: Vec::new(),
+        }])
+    );
+    assert_eq!(events[1], TailEvent::Reset(TailReset::Rotated));
+    assert_eq!(messages(&events[2..]), ["first after rotation"]);
+
+    let events = tail.poll().unwrap();
+    assert_eq!(messages(&events), ["second after rotation"]);
+    assert_eq!(records(&events)[0].line, 1);
+}
+
+#[test]
+fn test_tail_reads_a_backlog_over_several_polls() {
+    let temp_dir = TempDir::new().unwrap();
+    let log_path = temp_dir.path().join("MASQNode_rCURRENT.log");
+    let line = "2025-02-03 20:00:00.000 Thd1: INFO: Accountant: backlog\n";
+    let count = (2 * MAX_POLL_BYTES as usize) / line.len() + 1;
+    append(&log_path, &line.repeat(count));
+    let mut tail = LogTail::new(&log_path, 0, 0).unwrap();
+
+    // The old file is read out before its rotation is noticed
+    std::fs::rename(&log_path, temp_dir.path().join("MASQNode_r00000.log")).unwrap();
+    append(
+        &log_path,
+        "2025-02-03 20:00:01.000 Thd1: INFO: Bootstrapper: after rotation\n",
+    );
+
+    let mut lines = Vec::new();
+    let mut polls = 0;
+    let events = loop {
+        let events = tail.poll().unwrap();
+        polls += 1;
+        if events.contains(&TailEvent::Reset(TailReset::Rotated)) {
+            break events;
+        }
+        lines.extend(records(&events).into_iter().map(|r| r.line));
+    };
+    let (before, after) = events.split_at(
+        events
+            .iter()
+            .position(|e| *e == TailEvent::Reset(TailReset::Rotated))
+            .unwrap(),
+    );
+    lines.extend(records(before).into_iter().map(|r| r.line));
+    assert!(polls >= 3, "read in {} polls", polls);
+    assert_eq!(lines, (0..count).collect::<Vec<_>>());
+    assert!(messages(after).is_empty());
+    assert_eq!(messages(&tail.poll().unwrap()), ["after rotation"]);
+}
+
+#[tokio::test]
+async fn test_tail_endpoint_streams_filtered_records() {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use masq_log_visualizer::models::AllNodesData;
+    use masq_log_visualizer::routes::{tail_log, AppState};
+    use std::sync::{Arc, RwLock};
+    use tokio_stream::StreamExt;
+    use tower::ServiceExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    let node_dir = temp_dir.path().join("node1");
+    std::fs::create_dir(&node_dir).unwrap();
+    let log_path = node_dir.join("MASQNode_rCURRENT.log");
+    append(
+        &log_path,
+        "2025-02-03 20:00:00.000 Thd1: INFO: Bootstrapper: already served\n",
+    );
+
+    let app_state = AppState {
+        nodes_data: Arc::new(RwLock::new(AllNodesData::new())),
+        input_dir: temp_dir.path().to_path_buf(),
+        tera: Arc::new(tera::Tera::default()),
+        log_index: Arc::new(masq_log_visualizer::log_index::LogIndexCache::new(
+            temp_dir.path().join("cache"),
+        )),
+        redactor: Default::default(),
+        identities: Default::default(),
+    };
+    let app = axum::Router::new()
+        .route(
+            "/api/logs/:node_name/:file_name/tail",
+            axum::routing::get(tail_log),
+        )
+        .with_state(app_state);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/logs/node1/MASQNode_rCURRENT.log/tail?level=WARN")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+    append(
+        &log_path,
+        "2025-02-03 20:00:01.000 Thd1: INFO: Accountant: filtered out\n\
+         2025-02-03 20:00:02.000 Thd1: WARN: Accountant: kept\n",
+    );
+
+    let mut body = response.into_body().into_data_stream();
+    let mut received = String::new();
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(10);
+    while !received.contains("event: records") {
+        let chunk = tokio::time::timeout_at(deadline, body.next())
+            .await
+            .expect("no records streamed")
+            .unwrap()
+            .unwrap();
+        received.push_str(&String::from_utf8_lossy(&chunk));
+    }
+
+    assert!(received.contains("\"message\":\"kept\""));
+    assert!(!received.contains("filtered out"));
+    assert!(!received.contains("already served"));
+}
@@ -27,6 +27,9 @@ async fn test_flat_structure_template_rendering() {
         nodes_data: Arc::new(RwLock::new(nodes)),
         input_dir: test_dir,
         tera: Arc::new(tera),
+        log_index: Default::default(),
+        redactor: Default::default(),
+        identities: Default::default(),
     };
 
     let app = Router::new()
@@ -59,7 +62,7 @@ async fn test_flat_structure_template_rendering() {
     let body_str = std::str::from_utf8(&body_bytes).unwrap();
 
     // Check that the HTML contains the correct JavaScript variable
-    print!("Checking HTML for currentLogFile...\n");
+    println!("Checking HTML for currentLogFile...");
 
     // Should contain: const currentLogFile = "1-MASQNode_rCURRENT.log";
     assert!(
@@ -0,0 +1,92 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use masq_log_visualizer::log_index::LogIndexCache;
+use masq_log_visualizer::parser::scan_directory;
+use masq_log_visualizer::timeline::{node_color, node_log_sources, TimelineMerge};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn write_log(path: &Path, lines: &[&str]) {
+    let mut file = File::create(path).unwrap();
+    for line in lines {
+        writeln!(file, "{}", line).unwrap();
+    }
+}
+
+fn setup_nodes() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    let node_a = temp_dir.path().join("node_a");
+    let node_b = temp_dir.path().join("node_b");
+    std::fs::create_dir(&node_a).unwrap();
+    std::fs::create_dir(&node_b).unwrap();
+
+    // node_a: a rotated gzip archive followed by the current log
+    let mut encoder = GzEncoder::new(
+        File::create(node_a.join("MASQNode_r00000.log.zip")).unwrap(),
+        Compression::default(),
+    );
+    encoder
+        .write_all(
+            b"          banner\n\
+              2025-02-03 20:00:00.000 Thd1: INFO: Bootstrapper: a1\n\
+              2025-02-03 20:00:02.000 Thd1: INFO: Bootstrapper: a2\n",
+        )
+        .unwrap();
+    encoder.finish().unwrap();
+    write_log(
+        &node_a.join("MASQNode_rCURRENT.log"),
+        &[
+            "2025-02-03 20:00:04.000 Thd2: DEBUG: Neighborhood: a3",
+            "  continuation of a3",
+        ],
+    );
+
+    write_log(
+        &node_b.join("MASQNode_rCURRENT.log"),
+        &[
+            "2025-02-03 20:00:01.000 Thd3: WARN: Accountant: b1",
+            "2025-02-03 20:00:03.000 Thd3: INFO: Accountant: b2",
+            "2025-02-03 20:00:04.000 Thd3: INFO: Accountant: b3",
+        ],
+    );
+    temp_dir
+}
+
+#[test]
+fn test_timeline_merges_nodes_by_timestamp() {
+    let temp_dir = setup_nodes();
+    let nodes = scan_directory(temp_dir.path()).unwrap();
+    let cache = LogIndexCache::new(temp_dir.path().join("cache"));
+
+    let sources = node_log_sources(temp_dir.path(), &nodes, None);
+    let merged: Vec<_> = TimelineMerge::new(sources, &cache).collect();
+
+    let messages: Vec<&str> = merged.iter().map(|r| r.record.message.as_str()).collect();
+    // Banner has no timestamp and is left out; ties go to the first node
+    assert_eq!(messages, ["a1", "b1", "a2", "b2", "a3", "b3"]);
+
+    assert_eq!(merged[0].node, "node_a");
+    assert_eq!(merged[0].file, "MASQNode_r00000.log.zip");
+    assert_eq!(merged[0].color, node_color(0));
+    assert_eq!(merged[1].node, "node_b");
+    assert_eq!(merged[1].color, node_color(1));
+    assert_eq!(merged[4].record.continuation, ["  continuation of a3"]);
+}
+
+#[test]
+fn test_timeline_node_selection_keeps_colors() {
+    let temp_dir = setup_nodes();
+    let nodes = scan_directory(temp_dir.path()).unwrap();
+    let cache = LogIndexCache::new(temp_dir.path().join("cache"));
+
+    let only = ["node_b".to_string()];
+    let sources = node_log_sources(temp_dir.path(), &nodes, Some(&only));
+    let merged: Vec<_> = TimelineMerge::new(sources, &cache).collect();
+
+    assert_eq!(merged.len(), 3);
+    assert!(merged
+        .iter()
+        .all(|r| r.node == "node_b" && r.color == node_color(1)));
+}
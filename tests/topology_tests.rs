@@ -0,0 +1,154 @@
+use masq_log_visualizer::identity::IdentityRegistry;
+use masq_log_visualizer::models::{
+    AllNodesData, GossipEntry, NodeData, RouteDirection, RouteRecord,
+};
+use masq_log_visualizer::parser::{parse_node_config, scan_directory};
+use masq_log_visualizer::topology::build_topology;
+use std::path::PathBuf;
+
+mod common;
+use common::{descriptor, gossip};
+
+fn dump(second: u32, dot: &str) -> GossipEntry {
+    gossip(second, "Current database", dot)
+}
+
+fn route(hops: &[&str]) -> RouteRecord {
+    RouteRecord {
+        timestamp: "2025-02-03 20:00:10.000".to_string(),
+        direction: RouteDirection::Over,
+        hops: hops.iter().map(|hop| hop.to_string()).collect(),
+    }
+}
+
+fn network() -> AllNodesData {
+    common::network([
+        NodeData {
+            config: Some(parse_node_config(
+                "ip=\"1.1.1.1\"\nneighborhood-mode=\"standard\"\n",
+            )),
+            local_descriptors: descriptor("a-a_aaaa"),
+            gossip: vec![
+                // Superseded by the later dump
+                dump(
+                    0,
+                    r#"digraph db { "ffffffff" [label="AR v0 US\nffffffff"] }"#,
+                ),
+                dump(
+                    5,
+                    r#"digraph db { "a+a/aaaa" [label="AR v2 US\na+a/aaaa\n1.1.1.1:1000"] [style=filled]; "bbbbbbbb" [label="AR v1 US\nbbbbbbbb\n2.2.2.2:2000"]; "cccccccc" [label="AR v1 US\ncccccccc\n9.9.9.9:9000"]; "a+a/aaaa" -> "bbbbbbbb"; "a+a/aaaa" -> "cccccccc" }"#,
+                ),
+            ],
+            routes: vec![
+                route(&["a-a_aaaa", "bbbbbbbb", "cccccccc"]),
+                route(&["a-a_aaaa", "bbbbbbbb", "cccccccc"]),
+            ],
+            ..NodeData::new("node_a")
+        },
+        NodeData {
+            config: Some(parse_node_config(
+                "ip=\"2.2.2.2\"\nneighborhood-mode=\"consume-only\"\n",
+            )),
+            gossip: vec![dump(
+                3,
+                r#"digraph db { "bbbbbbbb" [label="AR v1 US\nbbbbbbbb\n2.2.2.2:2000"] [style=filled]; "cccccccc" [label="AR v3 FR\ncccccccc\n9.9.9.9:9000"]; "bbbbbbbb" -> "cccccccc" }"#,
+            )],
+            // The exit is abbreviated and unknown to any gossip
+            routes: vec![route(&["cccccccc", "gggggggg"])],
+            ..NodeData::new("node_b")
+        },
+    ])
+}
+
+#[test]
+fn test_build_topology() {
+    let nodes = network();
+    let topology = build_topology(&nodes, &IdentityRegistry::build(&nodes));
+
+    let keys: Vec<&str> = topology
+        .nodes
+        .iter()
+        .map(|n| n.public_key.as_str())
+        .collect();
+    assert_eq!(keys, ["a+a/aaaa", "bbbbbbbb", "cccccccc", "gggggggg"]);
+    let local: Vec<(Option<&str>, Option<&str>)> = topology
+        .nodes
+        .iter()
+        .map(|n| (n.node.as_deref(), n.neighborhood_mode.as_deref()))
+        .collect();
+    assert_eq!(
+        local,
+        [
+            (Some("node_a"), Some("standard")),
+            (Some("node_b"), Some("consume-only")),
+            (None, None),
+            (None, None),
+        ]
+    );
+    let versions: Vec<Option<u32>> = topology.nodes.iter().map(|n| n.version).collect();
+    assert_eq!(versions, [Some(2), Some(1), Some(3), None]);
+    let seen_by: Vec<&Vec<String>> = topology.nodes.iter().map(|n| &n.seen_by).collect();
+    assert_eq!(
+        seen_by,
+        [
+            &vec!["node_a".to_string()],
+            &vec!["node_a".to_string(), "node_b".to_string()],
+            &vec!["node_a".to_string(), "node_b".to_string()],
+            &vec![],
+        ]
+    );
+    // Attributes come from the highest version advertised
+    assert_eq!(topology.nodes[2].country_code.as_deref(), Some("FR"));
+
+    let edges: Vec<(&str, &str, Vec<&str>, usize)> = topology
+        .edges
+        .iter()
+        .map(|e| {
+            (
+                e.from.as_str(),
+                e.to.as_str(),
+                e.seen_by.iter().map(String::as_str).collect(),
+                e.route_count,
+            )
+        })
+        .collect();
+    assert_eq!(
+        edges,
+        [
+            ("a+a/aaaa", "bbbbbbbb", vec!["node_a"], 2),
+            ("a+a/aaaa", "cccccccc", vec!["node_a"], 0),
+            ("bbbbbbbb", "cccccccc", vec!["node_b"], 2),
+            ("cccccccc", "gggggggg", vec![], 1),
+        ]
+    );
+
+    let sources: Vec<(&str, &str)> = topology
+        .sources
+        .iter()
+        .map(|s| (s.node.as_str(), &s.timestamp[17..19]))
+        .collect();
+    assert_eq!(sources, [("node_a", "05"), ("node_b", "03")]);
+}
+
+#[test]
+fn test_topology_of_example_log() {
+    let test_dir = PathBuf::from("./Example-Log");
+    if !test_dir.exists() {
+        return;
+    }
+    let nodes = scan_directory(&test_dir).unwrap();
+    let topology = build_topology(&nodes, &IdentityRegistry::build(&nodes));
+
+    // Without gossip every local node is still shown, by its own key
+    for name in nodes.keys() {
+        assert!(
+            topology
+                .nodes
+                .iter()
+                .any(|n| n.node.as_deref() == Some(name.as_str())),
+            "{} is missing",
+            name
+        );
+    }
+    assert!(topology.sources.is_empty());
+}
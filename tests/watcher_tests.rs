@@ -0,0 +1,298 @@
+use masq_log_visualizer::identity::IdentityRegistry;
+use masq_log_visualizer::models::AllNodesData;
+use masq_log_visualizer::parser::scan_directory;
+use masq_log_visualizer::watcher::{spawn_watcher, NodeWatcher};
+use rusqlite::Connection;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+const GOSSIP_LINE: &str =
+    "2025-02-03 20:00:01.000 Thd1: DEBUG: Neighborhood: Sent Gossip: digraph db { \"a\" -> \"b\"; }\n";
+
+fn append(path: &Path, text: &str) {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .unwrap();
+    file.write_all(text.as_bytes()).unwrap();
+    file.flush().unwrap();
+}
+
+fn setup(temp_dir: &TempDir) -> (RwLock<AllNodesData>, NodeWatcher) {
+    let mut watcher = NodeWatcher::new(temp_dir.path().to_path_buf());
+    let nodes = scan_directory(temp_dir.path()).unwrap();
+    watcher.start_from(&nodes);
+    (RwLock::new(nodes), watcher)
+}
+
+#[test]
+fn test_watcher_parses_appended_lines_only() {
+    let temp_dir = TempDir::new().unwrap();
+    let node_dir = temp_dir.path().join("node_1");
+    std::fs::create_dir(&node_dir).unwrap();
+    let log_path = node_dir.join("MASQNode_rCURRENT.log");
+    append(&log_path, GOSSIP_LINE);
+
+    let (nodes, mut watcher) = setup(&temp_dir);
+    assert_eq!(nodes.read().unwrap()["node_1"].gossip.len(), 1);
+    assert!(watcher.poll(&nodes).is_empty());
+
+    append(
+        &log_path,
+        "2025-02-03 20:00:02.000 Thd1: DEBUG: Neighborhood: Received Gossip: digraph db { }\n\
+         2025-02-03 20:00:03.000 Thd1: DEBUG: Neighborhood: Sent Gossip: digraph db {",
+    );
+    assert_eq!(watcher.poll(&nodes), ["node_1"]);
+    {
+        let nodes = nodes.read().unwrap();
+        let node = &nodes["node_1"];
+        // The earlier gossip is not parsed again, the half-written line not yet
+        assert_eq!(node.gossip.len(), 2);
+        assert_eq!(node.gossip[1].tag, "Received Gossip");
+        assert!(node.current_log.contains("Received Gossip"));
+    }
+
+    // The completed line is the last record, so it waits for a quiet poll
+    append(&log_path, " }\n");
+    assert_eq!(watcher.poll(&nodes), ["node_1"]);
+    assert_eq!(nodes.read().unwrap()["node_1"].gossip.len(), 2);
+    assert_eq!(watcher.poll(&nodes), ["node_1"]);
+    assert!(watcher.poll(&nodes).is_empty());
+    let nodes = nodes.read().unwrap();
+    assert_eq!(nodes["node_1"].gossip.len(), 3);
+    assert_eq!(
+        nodes["node_1"].gossip[2].timestamp,
+        "2025-02-03 20:00:03.000"
+    );
+}
+
+#[test]
+fn test_watcher_picks_up_new_nodes_and_rotated_logs() {
+    let temp_dir = TempDir::new().unwrap();
+    let node_1 = temp_dir.path().join("node_1");
+    std::fs::create_dir(&node_1).unwrap();
+    append(&node_1.join("MASQNode_rCURRENT.log"), GOSSIP_LINE);
+
+    let (nodes, mut watcher) = setup(&temp_dir);
+
+    // A new node joins the test network
+    let node_2 = temp_dir.path().join("node_2");
+    std::fs::create_dir(&node_2).unwrap();
+    append(&node_2.join("MASQNode_rCURRENT.log"), GOSSIP_LINE);
+    assert_eq!(watcher.poll(&nodes), ["node_2"]);
+    assert_eq!(nodes.read().unwrap()["node_2"].gossip.len(), 1);
+
+    // node_1 rotates its log
+    std::fs::rename(
+        node_1.join("MASQNode_rCURRENT.log"),
+        node_1.join("MASQNode_r00000.log"),
+    )
+    .unwrap();
+    append(
+        &node_1.join("MASQNode_rCURRENT.log"),
+        "2025-02-03 20:00:05.000 Thd1: INFO: Bootstrapper: restarted\n",
+    );
+    assert_eq!(watcher.poll(&nodes), ["node_1"]);
+    assert_eq!(
+        nodes.read().unwrap()["node_1"].log_files,
+        ["MASQNode_r00000.log", "MASQNode_rCURRENT.log"]
+    );
+
+    // A node directory that disappears is dropped
+    std::fs::remove_dir_all(&node_2).unwrap();
+    assert_eq!(watcher.poll(&nodes), ["node_2"]);
+    assert!(!nodes.read().unwrap().contains_key("node_2"));
+}
+
+#[test]
+fn test_watcher_updates_database_and_flat_nodes() {
+    let temp_dir = TempDir::new().unwrap();
+    append(
+        &temp_dir.path().join("1-MASQNode_rCURRENT.log"),
+        GOSSIP_LINE,
+    );
+    let db_path = temp_dir.path().join("1-node-data.db");
+    Connection::open(&db_path)
+        .unwrap()
+        .execute("CREATE TABLE config (name TEXT)", [])
+        .unwrap();
+
+    let (nodes, mut watcher) = setup(&temp_dir);
+    assert!(nodes.read().unwrap()["1"]
+        .database
+        .tables
+        .contains_key("config"));
+
+    Connection::open(&db_path)
+        .unwrap()
+        .execute("CREATE TABLE payable (wallet_address TEXT)", [])
+        .unwrap();
+    append(
+        &temp_dir.path().join("2-MASQNode_rCURRENT.log"),
+        GOSSIP_LINE,
+    );
+
+    assert_eq!(watcher.poll(&nodes), ["1", "2"]);
+    {
+        let nodes = nodes.read().unwrap();
+        assert!(nodes["1"].database.tables.contains_key("payable"));
+        assert_eq!(nodes["1"].gossip.len(), 1);
+        assert_eq!(nodes["2"].gossip.len(), 1);
+    }
+
+    // Extracting the database structure leaves nothing behind to react to
+    assert!(watcher.poll(&nodes).is_empty());
+}
+
+#[test]
+fn test_watcher_keeps_multi_line_dumps_together() {
+    let temp_dir = TempDir::new().unwrap();
+    let node_dir = temp_dir.path().join("node_1");
+    std::fs::create_dir(&node_dir).unwrap();
+    let log_path = node_dir.join("MASQNode_rCURRENT.log");
+    append(&log_path, GOSSIP_LINE);
+    let (nodes, mut watcher) = setup(&temp_dir);
+
+    // The dump straddles two polls
+    append(
+        &log_path,
+        "2025-02-03 20:00:02.000 Thd1: DEBUG: Neighborhood: Current database: digraph db {\n\
+         \"a\" [label=\"AR v1\\na\"];\n",
+    );
+    watcher.poll(&nodes);
+    assert_eq!(nodes.read().unwrap()["node_1"].gossip.len(), 1);
+
+    append(
+        &log_path,
+        "\"a\" -> \"b\";\n\
+         }\n\
+         2025-02-03 20:00:03.000 Thd1: INFO: Accountant: next record\n",
+    );
+    watcher.poll(&nodes);
+    let nodes = nodes.read().unwrap();
+    let gossip = &nodes["node_1"].gossip;
+    assert_eq!(gossip.len(), 2);
+    assert_eq!(gossip[1].tag, "Current database");
+    assert!(gossip[1].dot.contains("\"a\" [label="));
+    assert!(gossip[1].dot.ends_with("\"a\" -> \"b\";\n}"));
+}
+
+#[test]
+fn test_watcher_continues_where_the_scan_stopped() {
+    let temp_dir = TempDir::new().unwrap();
+    let node_dir = temp_dir.path().join("node_1");
+    std::fs::create_dir(&node_dir).unwrap();
+    let log_path = node_dir.join("MASQNode_rCURRENT.log");
+    append(&log_path, GOSSIP_LINE);
+
+    // Lines written during a long scan: before it reads the log...
+    let mut watcher = NodeWatcher::new(temp_dir.path().to_path_buf());
+    append(&log_path, GOSSIP_LINE);
+    let nodes = scan_directory(temp_dir.path()).unwrap();
+    // ...and after
+    append(&log_path, GOSSIP_LINE);
+    watcher.start_from(&nodes);
+    assert_eq!(nodes["node_1"].gossip.len(), 2);
+
+    let nodes = RwLock::new(nodes);
+    watcher.poll(&nodes);
+    watcher.poll(&nodes);
+    // Every line parsed exactly once
+    assert_eq!(nodes.read().unwrap()["node_1"].gossip.len(), 3);
+}
+
+#[test]
+fn test_spawned_watcher_rebuilds_identities() {
+    let temp_dir = TempDir::new().unwrap();
+    let (nodes, watcher) = setup(&temp_dir);
+    let nodes = Arc::new(nodes);
+    let identities = Arc::new(RwLock::new(IdentityRegistry::build(&nodes.read().unwrap())));
+    spawn_watcher(
+        watcher,
+        nodes.clone(),
+        identities.clone(),
+        Duration::from_millis(10),
+    );
+
+    let node_dir = temp_dir.path().join("node_2");
+    std::fs::create_dir(&node_dir).unwrap();
+    append(
+        &node_dir.join("MASQNode_rCURRENT.log"),
+        "2025-02-03 20:00:00.000 Thd1: INFO: Bootstrapper: MASQ Node local descriptor: masq://polygon-amoy:AAAAaaaaBBBBbbbb@:\n",
+    );
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while identities
+        .read()
+        .unwrap()
+        .node_for("AAAAaaaaBBBBbbbb")
+        .is_none()
+    {
+        assert!(Instant::now() < deadline, "identities were not rebuilt");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(
+        identities.read().unwrap().node_for("AAAAaaaa"),
+        Some("node_2")
+    );
+}
+
+#[test]
+fn test_watcher_follows_database_writes_to_the_wal() {
+    let temp_dir = TempDir::new().unwrap();
+    let node_dir = temp_dir.path().join("node_1");
+    std::fs::create_dir(&node_dir).unwrap();
+    append(&node_dir.join("MASQNode_rCURRENT.log"), GOSSIP_LINE);
+    // The node keeps its database open, so writes stay in the WAL
+    let db = Connection::open(node_dir.join("node-data.db")).unwrap();
+    db.query_row("PRAGMA journal_mode=WAL", [], |_| Ok(()))
+        .unwrap();
+    db.execute("CREATE TABLE config (name TEXT)", []).unwrap();
+
+    let (nodes, mut watcher) = setup(&temp_dir);
+    assert!(watcher.poll(&nodes).is_empty());
+
+    db.execute("CREATE TABLE payable (wallet_address TEXT)", [])
+        .unwrap();
+    assert_eq!(watcher.poll(&nodes), ["node_1"]);
+    let nodes = nodes.read().unwrap();
+    let tables = &nodes["node_1"].database.tables;
+    assert!(tables.contains_key("config"));
+    assert!(tables.contains_key("payable"));
+}
+
+#[test]
+fn test_watcher_reparses_a_log_replaced_by_a_longer_one() {
+    let temp_dir = TempDir::new().unwrap();
+    let node_dir = temp_dir.path().join("node_1");
+    std::fs::create_dir(&node_dir).unwrap();
+    let log_path = node_dir.join("MASQNode_rCURRENT.log");
+    append(&log_path, GOSSIP_LINE);
+    let (nodes, mut watcher) = setup(&temp_dir);
+
+    // A new log takes its place, already longer than the old one
+    let new_log = node_dir.join("MASQNode_rCURRENT.log.new");
+    append(
+        &new_log,
+        "2025-02-03 20:00:10.000 Thd1: DEBUG: Neighborhood: Sent Gossip: digraph db { \"c\" -> \"d\"; }\n\
+         2025-02-03 20:00:11.000 Thd1: DEBUG: Neighborhood: Sent Gossip: digraph db { \"d\" -> \"c\"; }\n",
+    );
+    std::fs::rename(&new_log, &log_path).unwrap();
+
+    assert_eq!(watcher.poll(&nodes), ["node_1"]);
+    let nodes = nodes.read().unwrap();
+    let timestamps: Vec<&str> = nodes["node_1"]
+        .gossip
+        .iter()
+        .map(|g| g.timestamp.as_str())
+        .collect();
+    assert_eq!(
+        timestamps,
+        ["2025-02-03 20:00:10.000", "2025-02-03 20:00:11.000"]
+    );
+}